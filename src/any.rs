@@ -0,0 +1,210 @@
+use crate::reference::INTERNAL_VREF;
+use crate::{Channel, Command, Error};
+use core::convert::Infallible;
+use embedded_hal::spi::SpiDevice;
+
+/// Device variant, for selecting it at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKind {
+    /// MCP4801: 8-bit, single channel, internal reference
+    Mcp4801,
+    /// MCP4802: 8-bit, dual channel, internal reference
+    Mcp4802,
+    /// MCP4811: 10-bit, single channel, internal reference
+    Mcp4811,
+    /// MCP4812: 10-bit, dual channel, internal reference
+    Mcp4812,
+    /// MCP4821: 12-bit, single channel, internal reference
+    Mcp4821,
+    /// MCP4822: 12-bit, dual channel, internal reference
+    Mcp4822,
+    /// MCP4901: 8-bit, single channel, buffered external reference
+    Mcp4901,
+    /// MCP4902: 8-bit, dual channel, buffered external reference
+    Mcp4902,
+    /// MCP4911: 10-bit, single channel, buffered external reference
+    Mcp4911,
+    /// MCP4912: 10-bit, dual channel, buffered external reference
+    Mcp4912,
+    /// MCP4921: 12-bit, single channel, buffered external reference
+    Mcp4921,
+    /// MCP4922: 12-bit, dual channel, buffered external reference
+    Mcp4922,
+}
+
+impl DeviceKind {
+    /// All the device variants
+    pub const ALL: [DeviceKind; 12] = [
+        DeviceKind::Mcp4801,
+        DeviceKind::Mcp4802,
+        DeviceKind::Mcp4811,
+        DeviceKind::Mcp4812,
+        DeviceKind::Mcp4821,
+        DeviceKind::Mcp4822,
+        DeviceKind::Mcp4901,
+        DeviceKind::Mcp4902,
+        DeviceKind::Mcp4911,
+        DeviceKind::Mcp4912,
+        DeviceKind::Mcp4921,
+        DeviceKind::Mcp4922,
+    ];
+
+    /// Get the device variant from its name (e.g. "MCP4921"), ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Get the name of the device (e.g. "MCP4921")
+    pub fn name(self) -> &'static str {
+        match self {
+            DeviceKind::Mcp4801 => "MCP4801",
+            DeviceKind::Mcp4802 => "MCP4802",
+            DeviceKind::Mcp4811 => "MCP4811",
+            DeviceKind::Mcp4812 => "MCP4812",
+            DeviceKind::Mcp4821 => "MCP4821",
+            DeviceKind::Mcp4822 => "MCP4822",
+            DeviceKind::Mcp4901 => "MCP4901",
+            DeviceKind::Mcp4902 => "MCP4902",
+            DeviceKind::Mcp4911 => "MCP4911",
+            DeviceKind::Mcp4912 => "MCP4912",
+            DeviceKind::Mcp4921 => "MCP4921",
+            DeviceKind::Mcp4922 => "MCP4922",
+        }
+    }
+
+    /// Get the number of bits of the values
+    pub fn bits(self) -> u8 {
+        match self {
+            DeviceKind::Mcp4801 | DeviceKind::Mcp4802 => 8,
+            DeviceKind::Mcp4901 | DeviceKind::Mcp4902 => 8,
+            DeviceKind::Mcp4811 | DeviceKind::Mcp4812 => 10,
+            DeviceKind::Mcp4911 | DeviceKind::Mcp4912 => 10,
+            DeviceKind::Mcp4821 | DeviceKind::Mcp4822 => 12,
+            DeviceKind::Mcp4921 | DeviceKind::Mcp4922 => 12,
+        }
+    }
+
+    /// Get the number of channels
+    pub fn channels(self) -> u8 {
+        match self {
+            DeviceKind::Mcp4802 | DeviceKind::Mcp4812 | DeviceKind::Mcp4822 => 2,
+            DeviceKind::Mcp4902 | DeviceKind::Mcp4912 | DeviceKind::Mcp4922 => 2,
+            _ => 1,
+        }
+    }
+
+    /// Get whether buffered commands are supported (MCP49xx)
+    pub fn supports_buffering(self) -> bool {
+        self.internal_reference().is_none()
+    }
+
+    /// Get the voltage of the internal reference (MCP48xx)
+    ///
+    /// Returns `None` if the device uses an external reference (MCP49xx).
+    pub fn internal_reference(self) -> Option<f32> {
+        match self {
+            DeviceKind::Mcp4801
+            | DeviceKind::Mcp4802
+            | DeviceKind::Mcp4811
+            | DeviceKind::Mcp4812
+            | DeviceKind::Mcp4821
+            | DeviceKind::Mcp4822 => Some(INTERNAL_VREF),
+            _ => None,
+        }
+    }
+
+    /// Check that a command is appropriate for the device.
+    ///
+    /// The checks and errors are the same as in `Mcp49xx::send()`.
+    pub fn check<CommE, PinE>(self, command: Command) -> Result<(), Error<CommE, PinE>> {
+        use crate::marker::{
+            Buffered as Buf, DualChannel as Dual, Resolution10Bit as R10, Resolution12Bit as R12,
+            Resolution8Bit as R8, SingleChannel as Single, Unbuffered as Unbuf,
+        };
+        match self {
+            DeviceKind::Mcp4801 => command.check::<R8, Single, Unbuf, _, _>(),
+            DeviceKind::Mcp4802 => command.check::<R8, Dual, Unbuf, _, _>(),
+            DeviceKind::Mcp4811 => command.check::<R10, Single, Unbuf, _, _>(),
+            DeviceKind::Mcp4812 => command.check::<R10, Dual, Unbuf, _, _>(),
+            DeviceKind::Mcp4821 => command.check::<R12, Single, Unbuf, _, _>(),
+            DeviceKind::Mcp4822 => command.check::<R12, Dual, Unbuf, _, _>(),
+            DeviceKind::Mcp4901 => command.check::<R8, Single, Buf, _, _>(),
+            DeviceKind::Mcp4902 => command.check::<R8, Dual, Buf, _, _>(),
+            DeviceKind::Mcp4911 => command.check::<R10, Single, Buf, _, _>(),
+            DeviceKind::Mcp4912 => command.check::<R10, Dual, Buf, _, _>(),
+            DeviceKind::Mcp4921 => command.check::<R12, Single, Buf, _, _>(),
+            DeviceKind::Mcp4922 => command.check::<R12, Dual, Buf, _, _>(),
+        }
+    }
+
+    /// Check and encode a command into the frame sent to the device.
+    pub fn encode<CommE, PinE>(self, command: Command) -> Result<[u8; 2], Error<CommE, PinE>> {
+        self.check(command)?;
+        Ok(command.encode_bits(self.bits()))
+    }
+}
+
+/// Driver for a device variant selected at runtime
+///
+/// It performs the same checks as [`Mcp49xx`](crate::Mcp49xx) at runtime.
+/// See [`DeviceKind`].
+#[derive(Debug)]
+pub struct AnyMcp49xx<SPI> {
+    spi: SPI,
+    kind: DeviceKind,
+    shadow: [Option<Command>; 2],
+}
+
+impl<SPI> AnyMcp49xx<SPI> {
+    /// Create a new instance of a device.
+    pub fn new(spi: SPI, kind: DeviceKind) -> Self {
+        AnyMcp49xx {
+            spi,
+            kind,
+            shadow: [None; 2],
+        }
+    }
+
+    /// Destroy driver instance, return SPI device.
+    pub fn destroy(self) -> SPI {
+        self.spi
+    }
+
+    /// Get the device variant
+    pub fn kind(&self) -> DeviceKind {
+        self.kind
+    }
+
+    /// Get the last command sent to a channel.
+    ///
+    /// See [`Mcp49xx::current()`](crate::Mcp49xx::current).
+    pub fn current(&self, channel: Channel) -> Option<Command> {
+        self.shadow[channel.index()]
+    }
+}
+
+impl<SPI, CommE> AnyMcp49xx<SPI>
+where
+    SPI: SpiDevice<u8, Error = CommE>,
+{
+    /// Send command to device.
+    ///
+    /// The command is checked for the device variant and the same errors as in
+    /// [`Mcp49xx::send()`](crate::Mcp49xx::send) can be returned.
+    pub fn send(&mut self, command: Command) -> Result<(), Error<CommE>> {
+        let payload = self.kind.encode::<CommE, Infallible>(command)?;
+        self.write_payload(command, payload)
+    }
+
+    // write the frame of a checked command and track the state of the channel
+    fn write_payload(&mut self, command: Command, payload: [u8; 2]) -> Result<(), Error<CommE>> {
+        let index = command.channel.index();
+        self.shadow[index] = None;
+        self.spi.write(&payload).map_err(Error::Comm)?;
+        self.shadow[index] = Some(command);
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+//! Asynchronous driver for the `embedded-hal-async` traits.
+//!
+//! This driver offers the same interface as the blocking [`Mcp49xx`](crate::Mcp49xx)
+//! but commands are sent through an `embedded_hal_async::spi::SpiDevice`.
+//! It is available when enabling the `async` feature.
+
+use crate::{BufferingSupport, ChannelSupport, Command, Error, ResolutionSupport};
+use core::convert::Infallible;
+use core::marker::PhantomData;
+use embedded_hal_async::spi::SpiDevice;
+
+/// MCP49xx digital-to-analog converter asynchronous driver
+#[derive(Debug)]
+pub struct Mcp49xx<SPI, RES, CH, BUF> {
+    pub(crate) spi: SPI,
+    pub(crate) _resolution: PhantomData<RES>,
+    pub(crate) _channels: PhantomData<CH>,
+    pub(crate) _buffering: PhantomData<BUF>,
+}
+
+impl<SPI, RES, CH, BUF, CommE> Mcp49xx<SPI, RES, CH, BUF>
+where
+    SPI: SpiDevice<u8, Error = CommE>,
+    RES: ResolutionSupport<CommE, Infallible>,
+    CH: ChannelSupport<CommE, Infallible>,
+    BUF: BufferingSupport<CommE, Infallible>,
+{
+    /// Send command to device.
+    ///
+    /// This will return an error if the command is not appropriate for the current device:
+    /// - If the channel is not available it will return `Error::InvalidChannel`.
+    /// - If the value is too big it will return `Error::InvalidValue`.
+    /// - If buffering is not supported it will return `Error::BufferingNotSupported`.
+    ///
+    /// Otherwise if a communication error happened it will return `Error::Comm`.
+    pub async fn send(&mut self, command: Command) -> Result<(), Error<CommE>> {
+        command.check::<RES, CH, BUF, CommE, Infallible>()?;
+        let payload = command.encode::<RES, CommE, Infallible>();
+        self.spi.write(&payload).await.map_err(Error::Comm)
+    }
+}
@@ -0,0 +1,267 @@
+use crate::{
+    BufferingSupport, Channel, ChannelSupport, Command, Error, Mcp49xx, ResolutionSupport, State,
+};
+
+/// Maximum number of points in a calibration table
+pub const MAX_CALIBRATION_POINTS: usize = 8;
+
+/// Calibration of a channel
+///
+/// The calibration converts the value requested into the code actually
+/// sent to the device, compensating the offset and gain errors of the
+/// device and of the output stage:
+///
+/// `code = gain * value + offset`
+///
+/// Optionally, the result can be further corrected with a piecewise-linear
+/// table mapping codes to corrected codes. Values between the points are
+/// interpolated and values outside extrapolated from the first or last
+/// segment (a single point applies a constant correction).
+///
+/// The result is rounded to the closest code and clamped to the code
+/// limits of the device.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    offset: f32,
+    gain: f32,
+    points: [(u16, u16); MAX_CALIBRATION_POINTS],
+    point_count: usize,
+}
+
+impl Default for Calibration {
+    /// Create a calibration which does not change the values.
+    fn default() -> Self {
+        Calibration::new(0.0, 1.0)
+    }
+}
+
+impl Calibration {
+    /// Create a calibration from an offset (in codes) and a gain correction.
+    pub fn new(offset: f32, gain: f32) -> Self {
+        Calibration {
+            offset,
+            gain,
+            points: [(0, 0); MAX_CALIBRATION_POINTS],
+            point_count: 0,
+        }
+    }
+
+    /// Create a calibration from two measurements.
+    ///
+    /// Each measurement is the code sent and the code corresponding to the
+    /// output measured, i.e. the measured voltage divided by the LSB size.
+    /// Returns `None` if both codes sent are the same or the measurements
+    /// are the same, since then the gain cannot be determined.
+    pub fn from_measurements(
+        code_a: u16,
+        measured_a: f32,
+        code_b: u16,
+        measured_b: f32,
+    ) -> Option<Self> {
+        // The device outputs `measured = m * code + b` so `code = (measured - b) / m`
+        let m = (measured_b - measured_a) / (f32::from(code_b) - f32::from(code_a));
+        if !m.is_normal() {
+            return None;
+        }
+        let b = measured_a - m * f32::from(code_a);
+        Some(Calibration::new(-b / m, 1.0 / m))
+    }
+
+    /// Add a piecewise-linear correction table.
+    ///
+    /// Each point maps a code to a corrected code. The points must be sorted
+    /// by code without repetitions.
+    /// Returns `None` if the points are not sorted or there are more than
+    /// `MAX_CALIBRATION_POINTS`.
+    pub fn with_points(self, points: &[(u16, u16)]) -> Option<Self> {
+        if points.len() > MAX_CALIBRATION_POINTS || points.windows(2).any(|w| w[0].0 >= w[1].0) {
+            return None;
+        }
+        let mut cal = self;
+        cal.points[..points.len()].copy_from_slice(points);
+        cal.point_count = points.len();
+        Some(cal)
+    }
+
+    /// Get the offset (in codes)
+    pub fn offset(&self) -> f32 {
+        self.offset
+    }
+
+    /// Get the gain correction
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Get the piecewise-linear correction table
+    pub fn points(&self) -> &[(u16, u16)] {
+        &self.points[..self.point_count]
+    }
+
+    /// Get the code to send to a device with the given resolution in bits
+    /// for a requested value.
+    pub fn apply(&self, value: u16, bits: u8) -> u16 {
+        let code = f32::from(value) * self.gain + self.offset;
+        let code = self.interpolate(code);
+        let max = ((1_u32 << bits) - 1) as f32;
+        // NaN is converted to 0
+        (code.clamp(0.0, max) + 0.5) as u16
+    }
+
+    fn interpolate(&self, code: f32) -> f32 {
+        let points = self.points();
+        match points {
+            [] => code,
+            [(x, y)] => code + f32::from(*y) - f32::from(*x),
+            _ => {
+                let segment = points
+                    .windows(2)
+                    .position(|w| code < f32::from(w[1].0))
+                    .unwrap_or(points.len() - 2);
+                let (x0, y0) = points[segment];
+                let (x1, y1) = points[segment + 1];
+                let (x0, y0, x1, y1) = (f32::from(x0), f32::from(y0), f32::from(x1), f32::from(y1));
+                y0 + (code - x0) * (y1 - y0) / (x1 - x0)
+            }
+        }
+    }
+}
+
+impl State {
+    // apply the value policy, check the command and get the SPI frame with the calibrated value
+    // returns the command with the code after the value policy, before the calibration
+    pub(crate) fn get_payload<RES, CH, BUF, CommE, PinE>(
+        &self,
+        command: Command,
+    ) -> Result<(Command, [u8; 2]), Error<CommE, PinE>>
+    where
+        RES: ResolutionSupport<CommE, PinE>,
+        CH: ChannelSupport<CommE, PinE>,
+        BUF: BufferingSupport<CommE, PinE>,
+    {
+        let code = self
+            .value_policy
+            .apply(command.value, RES::BITS)
+            .ok_or(Error::InvalidValue)?;
+        let command = command.value(code);
+        let payload = self.get_code_payload::<RES, CH, BUF, CommE, PinE>(command)?;
+        Ok((command, payload))
+    }
+
+    // same as `get_payload()` for values computed by the driver, which are already codes
+    pub(crate) fn get_code_payload<RES, CH, BUF, CommE, PinE>(
+        &self,
+        command: Command,
+    ) -> Result<[u8; 2], Error<CommE, PinE>>
+    where
+        RES: ResolutionSupport<CommE, PinE>,
+        CH: ChannelSupport<CommE, PinE>,
+        BUF: BufferingSupport<CommE, PinE>,
+    {
+        command.check::<RES, CH, BUF, CommE, PinE>()?;
+        let command = match self.calibration[command.channel.index()] {
+            Some(cal) => command.value(cal.apply(command.value, RES::BITS)),
+            None => command,
+        };
+        Ok(command.encode::<RES, CommE, PinE>())
+    }
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN> {
+    /// Set the calibration of a channel.
+    ///
+    /// The calibration is applied to the values of all commands sent to the
+    /// channel afterwards, including the ones sent through the voltage methods.
+    pub fn set_calibration(&mut self, channel: Channel, calibration: Calibration) {
+        self.state.calibration[channel.index()] = Some(calibration);
+    }
+
+    /// Remove the calibration of a channel.
+    pub fn clear_calibration(&mut self, channel: Channel) {
+        self.state.calibration[channel.index()] = None;
+    }
+
+    /// Get the calibration of a channel.
+    pub fn calibration(&self, channel: Channel) -> Option<Calibration> {
+        self.state.calibration[channel.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_does_not_change_values() {
+        let cal = Calibration::default();
+        assert_eq!(cal.apply(0, 12), 0);
+        assert_eq!(cal.apply(1234, 12), 1234);
+        assert_eq!(cal.apply(4095, 12), 4095);
+    }
+
+    #[test]
+    fn applies_offset_and_gain() {
+        let cal = Calibration::new(-2.0, 1.01);
+        assert_eq!(cal.apply(100, 12), 99);
+        assert_eq!(cal.apply(1000, 12), 1008);
+    }
+
+    #[test]
+    fn clamps_to_code_limits() {
+        let cal = Calibration::new(5.0, 1.1);
+        assert_eq!(cal.apply(250, 8), 255);
+        let cal = Calibration::new(-5.0, 1.0);
+        assert_eq!(cal.apply(2, 10), 0);
+    }
+
+    #[test]
+    fn can_create_from_measurements() {
+        // output is 1% high with an offset of 3 codes
+        let cal = Calibration::from_measurements(100, 104.0, 1100, 1114.0).unwrap();
+        assert_eq!(cal.apply(104, 12), 100);
+        assert_eq!(cal.apply(1114, 12), 1100);
+        assert_eq!(cal.apply(507, 12), 499);
+    }
+
+    #[test]
+    fn cannot_create_from_invalid_measurements() {
+        assert_eq!(Calibration::from_measurements(100, 104.0, 100, 114.0), None);
+        assert_eq!(Calibration::from_measurements(100, 104.0, 200, 104.0), None);
+    }
+
+    #[test]
+    fn interpolates_points() {
+        let cal = Calibration::default()
+            .with_points(&[(0, 2), (100, 104), (200, 204)])
+            .unwrap();
+        assert_eq!(cal.points(), &[(0, 2), (100, 104), (200, 204)]);
+        assert_eq!(cal.apply(0, 8), 2);
+        assert_eq!(cal.apply(50, 8), 53);
+        assert_eq!(cal.apply(150, 8), 154);
+        // extrapolated from last segment and clamped
+        assert_eq!(cal.apply(250, 8), 254);
+        assert_eq!(cal.apply(255, 8), 255);
+    }
+
+    #[test]
+    fn single_point_applies_constant_correction() {
+        let cal = Calibration::default().with_points(&[(10, 7)]).unwrap();
+        assert_eq!(cal.apply(100, 10), 97);
+    }
+
+    #[test]
+    fn points_are_applied_after_offset_and_gain() {
+        let cal = Calibration::new(10.0, 1.0)
+            .with_points(&[(0, 0), (1000, 2000)])
+            .unwrap();
+        assert_eq!(cal.apply(90, 12), 200);
+    }
+
+    #[test]
+    fn rejects_invalid_points() {
+        let cal = Calibration::default();
+        assert_eq!(cal.with_points(&[(10, 7), (10, 8)]), None);
+        assert_eq!(cal.with_points(&[(10, 7), (5, 8)]), None);
+        assert_eq!(cal.with_points(&[(0, 0); MAX_CALIBRATION_POINTS + 1]), None);
+    }
+}
@@ -20,3 +20,50 @@ impl<CommE, PinE> ChannelSupport<CommE, PinE> for marker::DualChannel {
         Ok(())
     }
 }
+
+/// Channel 0 selected at compile time
+///
+/// Available in all devices. See [`ChannelOf`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Ch0;
+
+/// Channel 1 selected at compile time
+///
+/// Only available in dual devices (i.e. MCP4xx2). See [`ChannelOf`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Ch1;
+
+/// Channel selected at compile time which is available in devices with
+/// the channels marker `CH`
+///
+/// This is implemented for [`Ch0`] in all devices and for [`Ch1`] only in
+/// dual devices, so that using channel 1 of a single channel device fails
+/// to compile.
+pub trait ChannelOf<CH>: private::Sealed {
+    /// Selected channel
+    const CHANNEL: Channel;
+}
+
+impl ChannelOf<marker::SingleChannel> for Ch0 {
+    const CHANNEL: Channel = Channel::Ch0;
+}
+
+impl ChannelOf<marker::DualChannel> for Ch0 {
+    const CHANNEL: Channel = Channel::Ch0;
+}
+
+impl ChannelOf<marker::DualChannel> for Ch1 {
+    const CHANNEL: Channel = Channel::Ch1;
+}
+
+impl From<Ch0> for Channel {
+    fn from(_: Ch0) -> Self {
+        Channel::Ch0
+    }
+}
+
+impl From<Ch1> for Channel {
+    fn from(_: Ch1) -> Self {
+        Channel::Ch1
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{DeviceCommand, Resolution};
+use core::convert::TryFrom;
+
+/// DAC code with a number of bits
+///
+/// The value is always in the range `0..=Code::<BITS>::MAX`, so a code with
+/// the bit width of the device resolution does not need to be checked when
+/// sending it. The code type of each resolution is `Resolution::Code`
+/// (e.g. `Code<12>` for `marker::Resolution12Bit`).
+///
+/// `BITS` must be at most 16.
+///
+/// ```
+/// use mcp49xx::{Code, Command};
+///
+/// let code = Code::<10>::new(512).unwrap();
+/// assert_eq!(Code::<10>::new(1024), None);
+/// assert_eq!(Code::<10>::saturating(1024), Code::<10>::MAX);
+/// assert_eq!(Code::<10>::wrapping(1025).get(), 1);
+/// let cmd = Command::default().value(code.into());
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Code<const BITS: u8>(u16);
+
+impl<const BITS: u8> Code<BITS> {
+    /// Lowest code
+    pub const MIN: Self = Code(0);
+
+    /// Highest code
+    pub const MAX: Self = {
+        assert!(BITS <= 16, "Codes can have at most 16 bits");
+        Code(((1u32 << BITS) - 1) as u16)
+    };
+
+    /// Create a code if the value fits in `BITS` bits.
+    pub const fn new(value: u16) -> Option<Self> {
+        if value <= Self::MAX.0 {
+            Some(Code(value))
+        } else {
+            None
+        }
+    }
+
+    /// Create a code clamping the value to `MAX`.
+    pub const fn saturating(value: u16) -> Self {
+        if value <= Self::MAX.0 {
+            Code(value)
+        } else {
+            Self::MAX
+        }
+    }
+
+    /// Create a code keeping only the low `BITS` bits of the value.
+    pub const fn wrapping(value: u16) -> Self {
+        Code(value & Self::MAX.0)
+    }
+
+    /// Get the value
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+
+    /// Convert to a code with another number of bits clamping it to its `MAX`.
+    pub const fn saturating_into<const OTHER: u8>(self) -> Code<OTHER> {
+        Code::saturating(self.0)
+    }
+
+    /// Convert to a code with another number of bits keeping only its low bits.
+    pub const fn wrapping_into<const OTHER: u8>(self) -> Code<OTHER> {
+        Code::wrapping(self.0)
+    }
+}
+
+impl<const BITS: u8> From<Code<BITS>> for u16 {
+    fn from(code: Code<BITS>) -> Self {
+        code.0
+    }
+}
+
+impl<const BITS: u8> TryFrom<u16> for Code<BITS> {
+    type Error = CodeError;
+
+    fn try_from(value: u16) -> Result<Self, CodeError> {
+        Self::new(value).ok_or(CodeError::OutOfRange(value))
+    }
+}
+
+/// Errors creating a code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeError {
+    /// The value does not fit in the number of bits.
+    /// Contains the value.
+    OutOfRange(u16),
+}
+
+impl<RES: Resolution, CH, BUF> DeviceCommand<RES, CH, BUF> {
+    /// Set the value from a code with the resolution of the device
+    ///
+    /// The code is sent as it is with `send_command()`, the value policy does
+    /// not apply to it. Once converted into a `Command` it is a plain value.
+    ///
+    /// Codes of other bit widths fail to compile:
+    ///
+    /// ```compile_fail
+    /// use linux_embedded_hal::SpidevDevice;
+    /// use mcp49xx::{Code, Mcp49xx};
+    ///
+    /// let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+    /// let mut dac = Mcp49xx::new_mcp4801(spi);
+    /// let cmd = dac.command().code(Code::<12>::MAX);
+    /// dac.send_command(cmd).unwrap();
+    /// ```
+    pub fn code(self, code: RES::Code) -> Self {
+        let mut cmd = self.value(code.into());
+        cmd.code = true;
+        cmd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_depends_on_bits() {
+        assert_eq!(Code::<8>::MAX.get(), 255);
+        assert_eq!(Code::<10>::MAX.get(), 1023);
+        assert_eq!(Code::<12>::MAX.get(), 4095);
+        assert_eq!(Code::<16>::MAX.get(), 0xFFFF);
+        assert_eq!(Code::<12>::MIN.get(), 0);
+    }
+
+    #[test]
+    fn checks_range() {
+        assert_eq!(Code::<8>::new(255), Some(Code::<8>::MAX));
+        assert_eq!(Code::<8>::new(256), None);
+        assert_eq!(Code::<8>::try_from(256), Err(CodeError::OutOfRange(256)));
+        assert_eq!(Code::<12>::try_from(4095), Ok(Code::<12>::MAX));
+    }
+
+    #[test]
+    fn saturates() {
+        assert_eq!(Code::<10>::saturating(1023).get(), 1023);
+        assert_eq!(Code::<10>::saturating(0xFFFF).get(), 1023);
+        assert_eq!(Code::<12>::MAX.saturating_into::<8>(), Code::<8>::MAX);
+        assert_eq!(Code::<8>::MAX.saturating_into::<12>().get(), 255);
+    }
+
+    #[test]
+    fn wraps() {
+        assert_eq!(Code::<8>::wrapping(0x1FF).get(), 0xFF);
+        assert_eq!(Code::<8>::wrapping(0x100).get(), 0);
+        assert_eq!(
+            Code::<12>::new(0x123).unwrap().wrapping_into::<8>().get(),
+            0x23
+        );
+    }
+}
@@ -1,4 +1,7 @@
-use crate::Channel;
+use crate::{marker, BufferingSupport, Channel, ChannelSupport, Error, Mcp49xx, ResolutionSupport};
+use core::convert::Infallible;
+use core::marker::PhantomData;
+use embedded_hal::spi::SpiDevice;
 
 /// Configurable command that can be sent to the device
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -16,6 +19,15 @@ impl Default for Command {
     /// Per default the command is on channel 0, unbuffered, with single gain,
     /// enabled and with value 0.
     fn default() -> Self {
+        Command::new()
+    }
+}
+
+impl Command {
+    /// Create new command instance.
+    ///
+    /// This is the same as `Command::default()` but can be used in constants.
+    pub const fn new() -> Self {
         Command {
             channel: Channel::Ch0,
             buffered: false,
@@ -24,69 +36,92 @@ impl Default for Command {
             value: 0,
         }
     }
-}
 
-impl Command {
     /// Select the channel
-    pub fn channel(self, channel: Channel) -> Self {
+    pub const fn channel(self, channel: Channel) -> Self {
         let mut cmd = self;
         cmd.channel = channel;
         cmd
     }
 
     /// Shutdown the channel
-    pub fn shutdown(self) -> Self {
+    pub const fn shutdown(self) -> Self {
         let mut cmd = self;
         cmd.shutdown = true;
         cmd
     }
 
     /// Enable the channel (undo a shutdown)
-    pub fn enable(self) -> Self {
+    pub const fn enable(self) -> Self {
         let mut cmd = self;
         cmd.shutdown = false;
         cmd
     }
 
     /// Send the value buffered
-    pub fn buffered(self) -> Self {
+    pub const fn buffered(self) -> Self {
         let mut cmd = self;
         cmd.buffered = true;
         cmd
     }
 
     /// Send the value unbuffered
-    pub fn unbuffered(self) -> Self {
+    pub const fn unbuffered(self) -> Self {
         let mut cmd = self;
         cmd.buffered = false;
         cmd
     }
 
     /// Send the value with double gain (2x)
-    pub fn double_gain(self) -> Self {
+    pub const fn double_gain(self) -> Self {
         let mut cmd = self;
         cmd.double_gain = true;
         cmd
     }
 
     /// Send the value with single gain (1x)
-    pub fn single_gain(self) -> Self {
+    pub const fn single_gain(self) -> Self {
         let mut cmd = self;
         cmd.double_gain = false;
         cmd
     }
 
     /// Set the value
-    pub fn value(self, value: u16) -> Self {
+    pub const fn value(self, value: u16) -> Self {
         let mut cmd = self;
         cmd.value = value;
         cmd
     }
 
+    /// Get the selected channel
+    pub fn get_channel(&self) -> Channel {
+        self.channel
+    }
+
+    /// Get whether the value is sent buffered
+    pub fn is_buffered(&self) -> bool {
+        self.buffered
+    }
+
+    /// Get whether the value is sent with double gain (2x)
+    pub fn is_double_gain(&self) -> bool {
+        self.double_gain
+    }
+
+    /// Get whether the channel is shut down
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown
+    }
+
+    /// Get the value
+    pub fn get_value(&self) -> u16 {
+        self.value
+    }
+
     // get the config bits at the beginning of the command
-    pub(crate) fn get_config_bits(self) -> u8 {
+    pub(crate) const fn get_config_bits(self) -> u8 {
         let mut value = 0b0011_0000;
-        if self.channel != Channel::Ch0 {
+        if let Channel::Ch1 = self.channel {
             value |= 0b1000_0000;
         }
         if self.buffered {
@@ -100,6 +135,192 @@ impl Command {
         }
         value
     }
+
+    // get the command for the config bits at the beginning of a frame
+    pub(crate) fn from_config_bits(bits: u8) -> Self {
+        Command {
+            channel: if bits & 0b1000_0000 != 0 {
+                Channel::Ch1
+            } else {
+                Channel::Ch0
+            },
+            buffered: bits & 0b0100_0000 != 0,
+            double_gain: bits & 0b0010_0000 == 0,
+            shutdown: bits & 0b0001_0000 == 0,
+            value: 0,
+        }
+    }
+
+    // check the command is appropriate for the device
+    pub(crate) fn check<RES, CH, BUF, CommE, PinE>(self) -> Result<(), Error<CommE, PinE>>
+    where
+        RES: ResolutionSupport<CommE, PinE>,
+        CH: ChannelSupport<CommE, PinE>,
+        BUF: BufferingSupport<CommE, PinE>,
+    {
+        CH::check_channel_is_appropriate(self.channel)?;
+        RES::check_value_is_appropriate(self.value)?;
+        BUF::check_buffering_is_appropriate(self.buffered)
+    }
+
+    // get the SPI frame for a resolution of a command which has already been checked
+    pub(crate) const fn encode_bits(self, bits: u8) -> [u8; 2] {
+        let value = self.value << (12 - bits);
+        [self.get_config_bits() | (value >> 8) as u8, value as u8]
+    }
+
+    // get the SPI frame for a command which has already been checked
+    pub(crate) fn encode<RES, CommE, PinE>(self) -> [u8; 2]
+    where
+        RES: ResolutionSupport<CommE, PinE>,
+    {
+        let value = RES::get_value_for_spi(self.value);
+        [self.get_config_bits() | value[0], value[1]]
+    }
+}
+
+/// Command builder bound to a device type
+///
+/// It offers the same settings as [`Command`] but buffering can only be
+/// selected for devices supporting it (MCP49xx), so that trying to do it
+/// for MCP48xx devices fails to compile:
+///
+/// ```compile_fail
+/// use linux_embedded_hal::SpidevDevice;
+/// use mcp49xx::Mcp49xx;
+///
+/// let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+/// let mut dac = Mcp49xx::new_mcp4821(spi);
+/// let cmd = dac.command().buffered().value(1024);
+/// dac.send(cmd.into()).unwrap();
+/// ```
+///
+/// Created with `Mcp49xx::command()` and converted into a `Command` with `into()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeviceCommand<RES, CH, BUF> {
+    command: Command,
+    // whether the value is a code set with `code()`
+    pub(crate) code: bool,
+    _resolution: PhantomData<RES>,
+    _channels: PhantomData<CH>,
+    _buffering: PhantomData<BUF>,
+}
+
+impl<RES, CH, BUF> DeviceCommand<RES, CH, BUF> {
+    /// Create new command instance.
+    ///
+    /// The defaults are the same as in `Command::default()`.
+    pub fn new() -> Self {
+        DeviceCommand {
+            command: Command::new(),
+            code: false,
+            _resolution: PhantomData,
+            _channels: PhantomData,
+            _buffering: PhantomData,
+        }
+    }
+
+    pub(crate) fn map(self, f: impl FnOnce(Command) -> Command) -> Self {
+        DeviceCommand {
+            command: f(self.command),
+            ..self
+        }
+    }
+
+    /// Select the channel
+    pub fn channel(self, channel: Channel) -> Self {
+        self.map(|cmd| cmd.channel(channel))
+    }
+
+    /// Shutdown the channel
+    pub fn shutdown(self) -> Self {
+        self.map(Command::shutdown)
+    }
+
+    /// Enable the channel (undo a shutdown)
+    pub fn enable(self) -> Self {
+        self.map(Command::enable)
+    }
+
+    /// Send the value with double gain (2x)
+    pub fn double_gain(self) -> Self {
+        self.map(Command::double_gain)
+    }
+
+    /// Send the value with single gain (1x)
+    pub fn single_gain(self) -> Self {
+        self.map(Command::single_gain)
+    }
+
+    /// Set the value
+    pub fn value(self, value: u16) -> Self {
+        DeviceCommand {
+            code: false,
+            ..self.map(|cmd| cmd.value(value))
+        }
+    }
+
+    /// Get the command
+    pub fn command(&self) -> Command {
+        self.command
+    }
+}
+
+impl<RES, CH> DeviceCommand<RES, CH, marker::Buffered> {
+    /// Send the value buffered
+    pub fn buffered(self) -> Self {
+        self.map(Command::buffered)
+    }
+
+    /// Send the value unbuffered
+    pub fn unbuffered(self) -> Self {
+        self.map(Command::unbuffered)
+    }
+}
+
+impl<RES, CH, BUF> From<DeviceCommand<RES, CH, BUF>> for Command {
+    fn from(command: DeviceCommand<RES, CH, BUF>) -> Self {
+        command.command
+    }
+}
+
+impl<RES, CH, BUF> Default for DeviceCommand<RES, CH, BUF> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN> {
+    /// Create a command builder for this device type.
+    ///
+    /// See [`DeviceCommand`].
+    pub fn command(&self) -> DeviceCommand<RES, CH, BUF> {
+        DeviceCommand::new()
+    }
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN, CommE> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN>
+where
+    SPI: SpiDevice<u8, Error = CommE>,
+    RES: ResolutionSupport<CommE, Infallible>,
+    CH: ChannelSupport<CommE, Infallible>,
+    BUF: BufferingSupport<CommE, Infallible>,
+{
+    /// Send a command built with `command()`.
+    ///
+    /// This is the same as `send()` except that a code set with
+    /// `DeviceCommand::code()` is sent as it is, the value policy does not
+    /// apply to it.
+    pub fn send_command(
+        &mut self,
+        command: DeviceCommand<RES, CH, BUF>,
+    ) -> Result<(), Error<CommE>> {
+        if command.code {
+            self.send_code(command.command)
+        } else {
+            self.send(command.command)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -169,6 +390,21 @@ mod tests {
         check_cmd(cmd, Channel::Ch0, false, false, false, 1024);
     }
 
+    #[test]
+    fn getters_return_settings() {
+        let cmd = Command::default()
+            .channel(Channel::Ch1)
+            .buffered()
+            .double_gain()
+            .shutdown()
+            .value(1024);
+        assert_eq!(cmd.get_channel(), Channel::Ch1);
+        assert!(cmd.is_buffered());
+        assert!(cmd.is_double_gain());
+        assert!(cmd.is_shutdown());
+        assert_eq!(cmd.get_value(), 1024);
+    }
+
     #[test]
     fn operations_leave_original_command_unchanged() {
         let original = Command::default();
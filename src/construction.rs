@@ -1,7 +1,27 @@
-use crate::{marker, Mcp49xx};
+#[cfg(feature = "async")]
+use crate::asynch;
+#[cfg(feature = "eh02")]
+use crate::eh02;
+use crate::{marker, Mcp49xx, NoPin, State};
 use core::marker::PhantomData;
 
-impl<SPI, CS, RES, CH, BUF> Mcp49xx<CS, SPI, RES, CH, BUF> {
+impl<SPI, RES, CH, BUF> Mcp49xx<SPI, RES, CH, BUF> {
+    /// Destroy driver instance, return SPI device.
+    pub fn destroy(self) -> SPI {
+        self.spi
+    }
+}
+
+#[cfg(feature = "async")]
+impl<SPI, RES, CH, BUF> asynch::Mcp49xx<SPI, RES, CH, BUF> {
+    /// Destroy driver instance, return SPI device.
+    pub fn destroy(self) -> SPI {
+        self.spi
+    }
+}
+
+#[cfg(feature = "eh02")]
+impl<SPI, CS, RES, CH, BUF> eh02::Mcp49xx<CS, SPI, RES, CH, BUF> {
     /// Destroy driver instance, return CS output pin.
     pub fn destroy(self) -> CS {
         self.cs
@@ -17,10 +37,41 @@ macro_rules! impl_create {
     };
 
     ( @gen [$create:ident, $resolution:ident, $channels:ident, $buffering:ident, $doc:expr] ) => {
-        impl<SPI, CS> Mcp49xx<CS, SPI, marker::$resolution, marker::$channels, marker::$buffering> {
+        impl<SPI> Mcp49xx<SPI, marker::$resolution, marker::$channels, marker::$buffering> {
             #[doc = $doc]
-            pub fn $create(chip_select: CS) -> Self {
+            pub fn $create(spi: SPI) -> Self {
                 Mcp49xx {
+                    spi,
+                    ldac: NoPin,
+                    shdn: NoPin,
+                    state: State::default(),
+                    _resolution: PhantomData,
+                    _channels: PhantomData,
+                    _buffering: PhantomData,
+                }
+            }
+        }
+
+        #[cfg(feature = "async")]
+        impl<SPI> asynch::Mcp49xx<SPI, marker::$resolution, marker::$channels, marker::$buffering> {
+            #[doc = $doc]
+            pub fn $create(spi: SPI) -> Self {
+                asynch::Mcp49xx {
+                    spi,
+                    _resolution: PhantomData,
+                    _channels: PhantomData,
+                    _buffering: PhantomData,
+                }
+            }
+        }
+
+        #[cfg(feature = "eh02")]
+        impl<SPI, CS>
+            eh02::Mcp49xx<CS, SPI, marker::$resolution, marker::$channels, marker::$buffering>
+        {
+            #[doc = $doc]
+            pub fn $create(chip_select: CS) -> Self {
+                eh02::Mcp49xx {
                     cs: chip_select,
                     _spi: PhantomData,
                     _resolution: PhantomData,
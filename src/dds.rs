@@ -0,0 +1,242 @@
+//! Direct digital synthesis (DDS)
+//!
+//! A [`Dds`] produces the values of a periodic signal at an accurate
+//! frequency from a table containing one period of the signal. On every
+//! sample a 32-bit phase accumulator is advanced by a tuning word and the
+//! value is interpolated between the table entries around the phase.
+//!
+//! The frequency is only accurate if the samples are sent at the sample rate
+//! the tuning word was computed for, for example from a timer interrupt.
+//!
+//! ```no_run
+//! use linux_embedded_hal::SpidevDevice;
+//! use mcp49xx::{dds::Dds, marker::Resolution12Bit, waveform::Waveform};
+//! use mcp49xx::{Channel, Command, Mcp49xx};
+//!
+//! let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+//! let mut dac = Mcp49xx::new_mcp4922(spi);
+//! let mut table = [0; 64];
+//! Waveform::sine().fill::<Resolution12Bit>(&mut table);
+//!
+//! let mut dds = Dds::new(&table, Channel::Ch1).unwrap();
+//! // 440 Hz when sending 20000 samples per second
+//! dds.set_frequency(440.0, 20_000.0).unwrap();
+//! loop {
+//!     // wait for the next sample time...
+//!     dac.send_dds(&mut dds, Command::default()).unwrap();
+//! }
+//! ```
+
+use crate::{
+    BufferingSupport, Channel, ChannelSupport, Command, Error, Mcp49xx, ResolutionSupport,
+};
+use core::convert::Infallible;
+use embedded_hal::spi::SpiDevice;
+
+/// Direct digital synthesizer for a channel
+///
+/// The phase accumulator spans a whole period of the table over the `u32`
+/// range. The table can have any length. See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Dds<'a> {
+    table: &'a [u16],
+    channel: Channel,
+    phase: u32,
+    tuning_word: u32,
+}
+
+impl<'a> Dds<'a> {
+    /// Create a new synthesizer from a table with one period of the values.
+    ///
+    /// The phase and the tuning word are initially 0.
+    /// Returns `None` if the table is empty.
+    pub fn new(table: &'a [u16], channel: Channel) -> Option<Self> {
+        if table.is_empty() {
+            return None;
+        }
+        Some(Dds {
+            table,
+            channel,
+            phase: 0,
+            tuning_word: 0,
+        })
+    }
+
+    /// Compute the tuning word for a frequency at a sample rate (in Hz).
+    ///
+    /// Returns `None` if the frequency is negative or above the Nyquist
+    /// frequency (half the sample rate).
+    pub fn tuning_word(frequency: f32, sample_rate: f32) -> Option<u32> {
+        let ratio = f64::from(frequency) / f64::from(sample_rate);
+        // also rejects NaN
+        if !(0.0..=0.5).contains(&ratio) {
+            return None;
+        }
+        Some((ratio * (1u64 << 32) as f64 + 0.5) as u32)
+    }
+
+    /// Set the frequency of the output at a sample rate (in Hz).
+    ///
+    /// Returns `None` and keeps the previous frequency if the frequency is
+    /// negative or above the Nyquist frequency (half the sample rate).
+    pub fn set_frequency(&mut self, frequency: f32, sample_rate: f32) -> Option<()> {
+        self.tuning_word = Self::tuning_word(frequency, sample_rate)?;
+        Some(())
+    }
+
+    /// Get the frequency of the output at a sample rate (in Hz).
+    ///
+    /// This can differ slightly from the frequency set due to the tuning word resolution.
+    pub fn frequency(&self, sample_rate: f32) -> f32 {
+        (f64::from(self.tuning_word) * f64::from(sample_rate) / (1u64 << 32) as f64) as f32
+    }
+
+    /// Set the phase increment per sample.
+    pub fn set_tuning_word(&mut self, tuning_word: u32) {
+        self.tuning_word = tuning_word;
+    }
+
+    /// Get the phase increment per sample.
+    pub fn get_tuning_word(&self) -> u32 {
+        self.tuning_word
+    }
+
+    /// Set the phase of the next sample.
+    pub fn set_phase(&mut self, phase: u32) {
+        self.phase = phase;
+    }
+
+    /// Get the phase of the next sample.
+    pub fn get_phase(&self) -> u32 {
+        self.phase
+    }
+
+    /// Get the channel
+    pub fn get_channel(&self) -> Channel {
+        self.channel
+    }
+
+    /// Get the value at a phase, interpolating between the table entries.
+    pub fn code_at(&self, phase: u32) -> u16 {
+        let position = u64::from(phase) * self.table.len() as u64;
+        let index = (position >> 32) as usize;
+        let fraction = (position & 0xFFFF_FFFF) as i64;
+        let current = i64::from(self.table[index]);
+        let next = i64::from(self.table[(index + 1) % self.table.len()]);
+        (current + (((next - current) * fraction + (1 << 31)) >> 32)) as u16
+    }
+
+    /// Get the value of the next sample and advance the phase.
+    pub fn next_code(&mut self) -> u16 {
+        let code = self.code_at(self.phase);
+        self.phase = self.phase.wrapping_add(self.tuning_word);
+        code
+    }
+
+    /// Get the command for the next sample and advance the phase.
+    ///
+    /// The rest of the settings (gain, buffering...) are taken from the
+    /// template command. The value policy applies to the command when it
+    /// is sent with `send()`, use `send_dds()` to send the code as it is.
+    pub fn next_command(&mut self, template: Command) -> Command {
+        template.channel(self.channel).value(self.next_code())
+    }
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN, CommE> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN>
+where
+    SPI: SpiDevice<u8, Error = CommE>,
+    RES: ResolutionSupport<CommE, Infallible>,
+    CH: ChannelSupport<CommE, Infallible>,
+    BUF: BufferingSupport<CommE, Infallible>,
+{
+    /// Send the next sample of a synthesizer and advance its phase.
+    ///
+    /// The rest of the settings (gain, buffering...) are taken from the
+    /// template command. The sample is a code, so the value policy does not
+    /// apply to it. Otherwise, the same errors as in `send()` can be returned.
+    pub fn send_dds(&mut self, dds: &mut Dds, template: Command) -> Result<(), Error<CommE>> {
+        self.send_code(dds.next_command(template))
+    }
+}
+
+/// Endless iterator over the values of the samples
+impl<'a> Iterator for Dds<'a> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        Some(self.next_code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cannot_create_with_empty_table() {
+        assert!(Dds::new(&[], Channel::Ch0).is_none());
+    }
+
+    #[test]
+    fn computes_tuning_word() {
+        assert_eq!(Dds::tuning_word(0.0, 1000.0), Some(0));
+        assert_eq!(Dds::tuning_word(250.0, 1000.0), Some(1 << 30));
+        assert_eq!(Dds::tuning_word(500.0, 1000.0), Some(1 << 31));
+        assert_eq!(Dds::tuning_word(1.0, 3.0), Some(1_431_655_765));
+    }
+
+    #[test]
+    fn rejects_invalid_frequency() {
+        assert_eq!(Dds::tuning_word(-1.0, 1000.0), None);
+        assert_eq!(Dds::tuning_word(501.0, 1000.0), None);
+        assert_eq!(Dds::tuning_word(f32::NAN, 1000.0), None);
+        let mut dds = Dds::new(&[0], Channel::Ch0).unwrap();
+        dds.set_tuning_word(5);
+        assert_eq!(dds.set_frequency(600.0, 1000.0), None);
+        assert_eq!(dds.get_tuning_word(), 5);
+    }
+
+    #[test]
+    fn achieved_frequency_is_accurate() {
+        let mut dds = Dds::new(&[0], Channel::Ch0).unwrap();
+        dds.set_frequency(440.0, 44_100.0).unwrap();
+        assert!((dds.frequency(44_100.0) - 440.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn interpolates_between_entries() {
+        let dds = Dds::new(&[0, 100, 50], Channel::Ch0).unwrap();
+        let third = (1u64 << 32) / 3;
+        assert_eq!(dds.code_at(0), 0);
+        assert_eq!(dds.code_at((third / 2) as u32), 50);
+        assert_eq!(dds.code_at((third + 1) as u32), 100);
+        assert_eq!(dds.code_at((third * 5 / 2) as u32), 25);
+        assert_eq!(dds.code_at(u32::MAX), 0);
+    }
+
+    #[test]
+    fn advances_phase() {
+        let mut dds = Dds::new(&[0, 10, 20, 30], Channel::Ch1).unwrap();
+        dds.set_tuning_word(3 << 29);
+        let mut codes = [0; 6];
+        for code in codes.iter_mut() {
+            *code = dds.next_code();
+        }
+        assert_eq!(codes, [0, 15, 30, 5, 20, 15]);
+        assert_eq!(dds.get_phase(), (3u32 << 29).wrapping_mul(6));
+    }
+
+    #[test]
+    fn creates_commands_for_channel() {
+        let mut dds = Dds::new(&[7, 9], Channel::Ch1).unwrap();
+        let cmd = dds.next_command(Command::default().double_gain());
+        assert_eq!(
+            cmd,
+            Command::default()
+                .double_gain()
+                .channel(Channel::Ch1)
+                .value(7)
+        );
+    }
+}
@@ -0,0 +1,47 @@
+//! Driver for the `embedded-hal` 0.2 traits.
+//!
+//! This driver owns the chip select output pin and a reference to the SPI
+//! bus is passed in every call, as in previous versions of this crate.
+//! It is available when enabling the `eh02` feature.
+
+use crate::{BufferingSupport, ChannelSupport, Command, Error, ResolutionSupport};
+use core::marker::PhantomData;
+pub use embedded_hal_0_2::spi::{MODE_0, MODE_3};
+use embedded_hal_0_2::{blocking::spi::Write, digital::v2::OutputPin};
+
+/// MCP49xx digital-to-analog converter driver for `embedded-hal` 0.2
+#[derive(Debug)]
+pub struct Mcp49xx<CS, SPI, RES, CH, BUF> {
+    pub(crate) cs: CS,
+    pub(crate) _spi: PhantomData<SPI>,
+    pub(crate) _resolution: PhantomData<RES>,
+    pub(crate) _channels: PhantomData<CH>,
+    pub(crate) _buffering: PhantomData<BUF>,
+}
+
+impl<CS, SPI, RES, CH, BUF, CommE, PinE> Mcp49xx<CS, SPI, RES, CH, BUF>
+where
+    CS: OutputPin<Error = PinE>,
+    SPI: Write<u8, Error = CommE>,
+    RES: ResolutionSupport<CommE, PinE>,
+    CH: ChannelSupport<CommE, PinE>,
+    BUF: BufferingSupport<CommE, PinE>,
+{
+    /// Send command to device.
+    ///
+    /// This will return an error if the command is not appropriate for the current device:
+    /// - If the channel is not available it will return `Error::InvalidChannel`.
+    /// - If the value is too big it will return `Error::InvalidValue`.
+    /// - If buffering is not supported it will return `Error::BufferingNotSupported`.
+    ///
+    /// Otherwise if a communication error happened it will return `Error::Comm`.
+    pub fn send(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<CommE, PinE>> {
+        command.check::<RES, CH, BUF, CommE, PinE>()?;
+        let payload = command.encode::<RES, CommE, PinE>();
+
+        self.cs.set_low().map_err(Error::Pin)?;
+        let result = spi.write(&payload).map_err(Error::Comm);
+        self.cs.set_high().map_err(Error::Pin)?;
+        result
+    }
+}
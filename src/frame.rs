@@ -0,0 +1,226 @@
+use crate::{
+    BufferingSupport, ChannelSupport, Command, Error, Mcp49xx, Resolution, ResolutionSupport,
+};
+use core::convert::{Infallible, TryFrom};
+use core::marker::PhantomData;
+use embedded_hal::spi::SpiDevice;
+
+/// SPI frame decoded for a resolution
+///
+/// This is the inverse of the encoding done in `send()` and can be created
+/// from the two bytes on the wire or from a 16-bit word (MSB first) with
+/// `TryFrom`:
+///
+/// ```
+/// use core::convert::TryFrom;
+/// use mcp49xx::{marker::Resolution10Bit, Channel, Command, DecodedFrame};
+///
+/// let frame = DecodedFrame::<Resolution10Bit>::try_from([0b1001_0000, 0b0000_0100]).unwrap();
+/// assert_eq!(frame.code(), 1);
+/// assert_eq!(frame.command(), Command::default().channel(Channel::Ch1).double_gain().value(1));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecodedFrame<RES> {
+    command: Command,
+    _resolution: PhantomData<RES>,
+}
+
+impl<RES> DecodedFrame<RES> {
+    /// Get the command, including the code as value
+    pub fn command(&self) -> Command {
+        self.command
+    }
+
+    /// Get the raw code sent
+    ///
+    /// If a calibration was set, this is the value after calibration.
+    pub fn code(&self) -> u16 {
+        self.command.value
+    }
+}
+
+impl<RES: Resolution> TryFrom<[u8; 2]> for DecodedFrame<RES> {
+    type Error = FrameError;
+
+    /// Decode the two bytes on the wire.
+    ///
+    /// If the low bits not used by the resolution are not zero, it will return
+    /// `FrameError::UnusedBitsSet` with the command decoded ignoring them.
+    fn try_from(frame: [u8; 2]) -> Result<Self, FrameError> {
+        Self::try_from(u16::from_be_bytes(frame))
+    }
+}
+
+impl<RES: Resolution> TryFrom<u16> for DecodedFrame<RES> {
+    type Error = FrameError;
+
+    /// Decode a 16-bit word (MSB first).
+    ///
+    /// If the low bits not used by the resolution are not zero, it will return
+    /// `FrameError::UnusedBitsSet` with the command decoded ignoring them.
+    fn try_from(frame: u16) -> Result<Self, FrameError> {
+        let unused_bits = 12 - RES::BITS;
+        let command =
+            Command::from_config_bits((frame >> 8) as u8).value((frame & 0x0FFF) >> unused_bits);
+        if frame & ((1 << unused_bits) - 1) != 0 {
+            return Err(FrameError::UnusedBitsSet(command));
+        }
+        Ok(DecodedFrame {
+            command,
+            _resolution: PhantomData,
+        })
+    }
+}
+
+/// Errors decoding a frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameError {
+    /// The low bits not used by the resolution are not zero.
+    /// Contains the command decoded ignoring them.
+    UnusedBitsSet(Command),
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN, CommE> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN>
+where
+    SPI: SpiDevice<u8, Error = CommE>,
+    RES: ResolutionSupport<CommE, Infallible>,
+    CH: ChannelSupport<CommE, Infallible>,
+    BUF: BufferingSupport<CommE, Infallible>,
+{
+    /// Encode commands into a buffer of bytes in wire order.
+    ///
+    /// Each command takes two bytes, which are the same that `send()` would write.
+    /// This is useful to send the commands later on, for example with DMA.
+    /// Returns the number of bytes written.
+    ///
+    /// The same errors as in `send()` can be returned (except `Error::Comm`).
+    /// If the buffer cannot hold all the frames it will return `Error::BufferTooSmall`.
+    /// The content of the buffer is unspecified after an error.
+    ///
+    /// If a calibration has been set for the channel, it is applied to the value.
+    /// The current state of the channels is not changed.
+    pub fn encode<I>(&self, commands: I, buffer: &mut [u8]) -> Result<usize, Error<CommE>>
+    where
+        I: IntoIterator<Item = Command>,
+    {
+        let mut chunks = buffer.chunks_exact_mut(2);
+        let mut len = 0;
+        for command in commands {
+            let chunk = chunks.next().ok_or(Error::BufferTooSmall)?;
+            chunk.copy_from_slice(&self.encode_frame(command)?);
+            len += 2;
+        }
+        Ok(len)
+    }
+
+    /// Encode commands into a buffer of 16-bit words in wire order.
+    ///
+    /// Each command takes one word, to be sent with 16-bit SPI frames (MSB first).
+    /// Returns the number of words written.
+    ///
+    /// See [`encode()`](Mcp49xx::encode) for the rest of the details.
+    pub fn encode_words<I>(&self, commands: I, buffer: &mut [u16]) -> Result<usize, Error<CommE>>
+    where
+        I: IntoIterator<Item = Command>,
+    {
+        let mut words = buffer.iter_mut();
+        let mut len = 0;
+        for command in commands {
+            let word = words.next().ok_or(Error::BufferTooSmall)?;
+            *word = u16::from_be_bytes(self.encode_frame(command)?);
+            len += 1;
+        }
+        Ok(len)
+    }
+
+    /// Encode values with the rest of the settings (channel, gain,
+    /// buffering...) taken from a command into a buffer of bytes in wire order.
+    ///
+    /// See [`encode()`](Mcp49xx::encode).
+    pub fn encode_values(
+        &self,
+        command: Command,
+        values: &[u16],
+        buffer: &mut [u8],
+    ) -> Result<usize, Error<CommE>> {
+        self.encode(values.iter().map(|value| command.value(*value)), buffer)
+    }
+
+    /// Encode values with the rest of the settings (channel, gain,
+    /// buffering...) taken from a command into a buffer of 16-bit words in wire order.
+    ///
+    /// See [`encode_words()`](Mcp49xx::encode_words).
+    pub fn encode_value_words(
+        &self,
+        command: Command,
+        values: &[u16],
+        buffer: &mut [u16],
+    ) -> Result<usize, Error<CommE>> {
+        self.encode_words(values.iter().map(|value| command.value(*value)), buffer)
+    }
+
+    fn encode_frame(&self, command: Command) -> Result<[u8; 2], Error<CommE>> {
+        self.state
+            .get_payload::<RES, CH, BUF, CommE, Infallible>(command)
+            .map(|(_, frame)| frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::marker::{Resolution10Bit, Resolution12Bit, Resolution8Bit};
+    use crate::Channel;
+
+    fn commands() -> impl Iterator<Item = Command> {
+        (0..16u8).map(|bits| {
+            let mut cmd = Command::default();
+            if bits & 1 != 0 {
+                cmd = cmd.channel(Channel::Ch1);
+            }
+            if bits & 2 != 0 {
+                cmd = cmd.buffered();
+            }
+            if bits & 4 != 0 {
+                cmd = cmd.double_gain();
+            }
+            if bits & 8 != 0 {
+                cmd = cmd.shutdown();
+            }
+            cmd
+        })
+    }
+
+    fn assert_roundtrip<RES: ResolutionSupport<(), ()>>(value: u16) {
+        for cmd in commands() {
+            let cmd = cmd.value(value);
+            let frame = cmd.encode::<RES, (), ()>();
+            let decoded = DecodedFrame::<RES>::try_from(frame).unwrap();
+            assert_eq!(decoded.command(), cmd);
+            assert_eq!(decoded.code(), value);
+            let decoded = DecodedFrame::<RES>::try_from(u16::from_be_bytes(frame)).unwrap();
+            assert_eq!(decoded.command(), cmd);
+        }
+    }
+
+    #[test]
+    fn can_decode_encoded_frames() {
+        assert_roundtrip::<Resolution12Bit>(0xABC);
+        assert_roundtrip::<Resolution10Bit>(0x2AB);
+        assert_roundtrip::<Resolution8Bit>(0xAB);
+    }
+
+    #[test]
+    fn reports_unused_bits_set() {
+        let expected = Command::default().value(0xFF);
+        assert_eq!(
+            DecodedFrame::<Resolution8Bit>::try_from(0b0011_1111_1111_0001),
+            Err(FrameError::UnusedBitsSet(expected))
+        );
+        assert_eq!(
+            DecodedFrame::<Resolution10Bit>::try_from([0b0011_0000, 0b0000_0010]),
+            Err(FrameError::UnusedBitsSet(Command::default()))
+        );
+        assert!(DecodedFrame::<Resolution12Bit>::try_from(0b0011_0000_0000_0011).is_ok());
+    }
+}
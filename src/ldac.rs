@@ -0,0 +1,100 @@
+use crate::{marker, BufferingSupport, Channel, Command, Error, Mcp49xx, NoPin, ResolutionSupport};
+use core::marker::PhantomData;
+use embedded_hal::{delay::DelayNs, digital::OutputPin, spi::SpiDevice};
+
+/// Minimum time from CS rising edge to LDAC falling edge (t_LS)
+const LDAC_SETUP_TIME_NS: u32 = 40;
+/// Minimum LDAC low pulse width (t_LD)
+const LDAC_PULSE_WIDTH_NS: u32 = 100;
+
+impl<SPI, RES, CH, BUF, SHDN> Mcp49xx<SPI, RES, CH, BUF, NoPin, SHDN> {
+    /// Attach the LDAC output pin to the driver.
+    ///
+    /// The pin state is not changed. It should be low so that `send()`
+    /// updates the outputs immediately, as if LDAC was tied to ground.
+    pub fn with_ldac<LDAC>(self, ldac: LDAC) -> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN> {
+        Mcp49xx {
+            spi: self.spi,
+            ldac,
+            shdn: self.shdn,
+            state: self.state,
+            _resolution: PhantomData,
+            _channels: PhantomData,
+            _buffering: PhantomData,
+        }
+    }
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN> {
+    /// Detach the LDAC output pin from the driver and return it.
+    pub fn release_ldac(self) -> (Mcp49xx<SPI, RES, CH, BUF, NoPin, SHDN>, LDAC) {
+        let dev = Mcp49xx {
+            spi: self.spi,
+            ldac: NoPin,
+            shdn: self.shdn,
+            state: self.state,
+            _resolution: PhantomData,
+            _channels: PhantomData,
+            _buffering: PhantomData,
+        };
+        (dev, self.ldac)
+    }
+}
+
+impl<SPI, RES, BUF, LDAC, SHDN, CommE, PinE> Mcp49xx<SPI, RES, marker::DualChannel, BUF, LDAC, SHDN>
+where
+    SPI: SpiDevice<u8, Error = CommE>,
+    LDAC: OutputPin<Error = PinE>,
+    RES: ResolutionSupport<CommE, PinE>,
+    BUF: BufferingSupport<CommE, PinE>,
+{
+    /// Send a command to each channel and update both outputs at the same time.
+    ///
+    /// LDAC is held high while the commands are written to the input
+    /// registers of channel 0 and channel 1, respectively. Then LDAC is
+    /// brought low, which transfers both input registers to the outputs.
+    /// The delay is used to meet the LDAC timing requirements. LDAC is
+    /// left low afterwards.
+    ///
+    /// The channel selected in the commands is ignored.
+    ///
+    /// Both commands are checked before any communication takes place and
+    /// the same errors as in `send()` can be returned.
+    /// Additionally, if setting the LDAC pin fails it will return `Error::Pin`.
+    /// If an error happens once the communication has started, the state of
+    /// both channels is unknown. After a communication error, LDAC is
+    /// brought low before returning.
+    pub fn send_synchronized<D: DelayNs>(
+        &mut self,
+        ch0: Command,
+        ch1: Command,
+        delay: &mut D,
+    ) -> Result<(), Error<CommE, PinE>> {
+        let ch0 = ch0.channel(Channel::Ch0);
+        let ch1 = ch1.channel(Channel::Ch1);
+        let (ch0, payload0) = self
+            .state
+            .get_payload::<RES, marker::DualChannel, BUF, CommE, PinE>(ch0)?;
+        let (ch1, payload1) = self
+            .state
+            .get_payload::<RES, marker::DualChannel, BUF, CommE, PinE>(ch1)?;
+
+        self.ldac.set_high().map_err(Error::Pin)?;
+        self.state.shadow = [None; 2];
+        let written = self
+            .spi
+            .write(&payload0)
+            .and_then(|_| self.spi.write(&payload1));
+        if let Err(e) = written {
+            // do not leave LDAC high, otherwise later commands would never
+            // reach the outputs
+            let _ = self.ldac.set_low();
+            return Err(Error::Comm(e));
+        }
+        delay.delay_ns(LDAC_SETUP_TIME_NS);
+        self.ldac.set_low().map_err(Error::Pin)?;
+        delay.delay_ns(LDAC_PULSE_WIDTH_NS);
+        self.state.shadow = [Some(ch0), Some(ch1)];
+        Ok(())
+    }
+}
@@ -5,9 +5,28 @@
 //!
 //! This driver allows you to:
 //! - Set a channel to a value.
+//! - Get the last command sent to a channel.
+//! - Prepare commands in advance for fast repeated output.
+//! - Encode commands into buffers, for example to send them with DMA.
+//! - Decode frames back into commands.
 //! - Shutdown a channel.
 //! - Use buffering on commands.
 //! - Select gain.
+//! - Set a channel to a voltage.
+//! - Calibrate the offset and gain errors of each channel.
+//! - Store and load the calibration in non-volatile memory.
+//! - Update both channels synchronously using the LDAC pin.
+//! - Split dual channel devices into independent channel handles.
+//! - Shutdown the device using the SHDN pin.
+//! - Generate sine, triangle, sawtooth and square waveforms.
+//! - Synthesize signals at accurate frequencies (DDS).
+//! - Create waveform tables and their SPI frames at compile time.
+//! - Simulate the devices for testing without hardware.
+//! - Select the device variant at runtime.
+//! - Build commands checked against the device capabilities at compile time.
+//! - Use DAC codes typed with the bit width of the device.
+//! - Saturate out-of-range values or scale them from 16-bit or normalized values.
+//! - Set outputs as a fraction of the full scale independently of the resolution.
 //!
 //! ## The devices
 //! The Microchip Technology Inc. MCP49xx and MCP48xx devices are single/dual
@@ -73,6 +92,12 @@
 //! as an example. Other devices can be created with similar methods like:
 //! `Mcp49xx::new_mcp4822(...)`.
 //!
+//! The driver takes ownership of an `embedded_hal::spi::SpiDevice`, which
+//! manages the chip select line. Sharing the SPI bus with other devices is
+//! possible through the implementations in [`embedded-hal-bus`].
+//!
+//! [`embedded-hal-bus`]: https://crates.io/crates/embedded-hal-bus
+//!
 //! Please find additional examples using hardware in this repository: [driver-examples]
 //!
 //! [driver-examples]: https://github.com/eldruin/driver-examples
@@ -81,73 +106,269 @@
 //!
 //! ```no_run
 //! use mcp49xx::{Channel, Command, Mcp49xx};
-//! use linux_embedded_hal::{Pin, Spidev};
+//! use linux_embedded_hal::SpidevDevice;
 //!
-//! let mut spi = Spidev::open("/dev/spidev0.0").unwrap();
-//! let chip_select = Pin::new(25);
-//!
-//! let mut dac = Mcp49xx::new_mcp4921(chip_select);
+//! let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+//! let mut dac = Mcp49xx::new_mcp4921(spi);
 //!
 //! let cmd = Command::default();
 //! let cmd = cmd.channel(Channel::Ch0).value(1024);
-//! dac.send(&mut spi, cmd).unwrap();
+//! dac.send(cmd).unwrap();
 //!
-//! // Get CS pin back
-//! let _chip_select = dac.destroy();
+//! // Get SPI device back
+//! let _spi = dac.destroy();
+//! ```
+//!
+//! ### Select the device at runtime
+//!
+//! ```no_run
+//! use mcp49xx::{AnyMcp49xx, Command, DeviceKind};
+//! use linux_embedded_hal::SpidevDevice;
+//!
+//! // e.g. read from a configuration file
+//! let kind = DeviceKind::from_name("MCP4812").unwrap();
+//! let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+//! let mut dac = AnyMcp49xx::new(spi, kind);
+//!
+//! let max = (1 << kind.bits()) - 1;
+//! dac.send(Command::default().value(max)).unwrap();
+//! ```
+//!
+//! ### Set the output of a MCP48x1 device independently of its resolution
+//!
+//! ```no_run
+//! use mcp49xx::{Command, Mcp49xx, Normalized};
+//! use linux_embedded_hal::SpidevDevice;
+//!
+//! let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+//! // the same value works for `new_mcp4801()` and `new_mcp4811()`
+//! let mut dac = Mcp49xx::new_mcp4821(spi);
+//! let value = Normalized::from_f32(0.75).unwrap();
+//! dac.send_normalized(Command::default(), value).unwrap();
 //! ```
 //!
 //! ### Set position and shutdown channels in a MCP4822 device
 //!
 //! ```no_run
 //! use mcp49xx::{Channel, Command, Mcp49xx};
-//! use linux_embedded_hal::{Pin, Spidev};
+//! use linux_embedded_hal::SpidevDevice;
 //!
-//! let mut spi = Spidev::open("/dev/spidev0.0").unwrap();
-//! let chip_select = Pin::new(25);
-//!
-//! let mut dac = Mcp49xx::new_mcp4822(chip_select);
+//! let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+//! let mut dac = Mcp49xx::new_mcp4822(spi);
 //!
 //! let cmd = Command::default();
 //! let cmd = cmd.channel(Channel::Ch1).value(1024);
-//! dac.send(&mut spi, cmd).unwrap();
+//! dac.send(cmd).unwrap();
 //!
 //! let cmd = Command::default();
 //! let cmd = cmd.channel(Channel::Ch0).shutdown();
-//! dac.send(&mut spi, cmd).unwrap();
+//! dac.send(cmd).unwrap();
 //!
-//! // Get CS pin back
-//! let _chip_select = dac.destroy();
+//! // Get SPI device back
+//! let _spi = dac.destroy();
 //! ```
 //!
 //! ### Set position and activate buffering and double gain in a MCP4911 device
 //!
 //! ```no_run
 //! use mcp49xx::{Channel, Command, Mcp49xx};
-//! use linux_embedded_hal::{Pin, Spidev};
+//! use linux_embedded_hal::SpidevDevice;
 //!
-//! let mut spi = Spidev::open("/dev/spidev0.0").unwrap();
-//! let chip_select = Pin::new(25);
-//!
-//! let mut dac = Mcp49xx::new_mcp4911(chip_select);
+//! let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+//! let mut dac = Mcp49xx::new_mcp4911(spi);
 //!
 //! let cmd = Command::default();
 //! let cmd = cmd.channel(Channel::Ch0).buffered().double_gain().value(511);
+//! dac.send(cmd).unwrap();
+//!
+//! // Get SPI device back
+//! let _spi = dac.destroy();
+//! ```
+//!
+//! ### Set channel 1 of a MCP4922 device to 1.5V with a 2.5V reference
+//!
+//! ```no_run
+//! use mcp49xx::{Channel, Mcp49xx};
+//! use linux_embedded_hal::SpidevDevice;
+//!
+//! let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+//! let mut dac = Mcp49xx::new_mcp4922(spi);
+//! dac.set_vref(2.5);
+//!
+//! let achieved = dac.set_voltage(Channel::Ch1, 1.5).unwrap();
+//! println!("Output voltage: {}V", achieved);
+//! ```
+//!
+//! ### Use different references for each channel of a MCP4912 device
+//!
+//! ```no_run
+//! use mcp49xx::{Channel, Mcp49xx};
+//! use linux_embedded_hal::SpidevDevice;
+//!
+//! let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+//! let mut dac = Mcp49xx::new_mcp4912(spi);
+//! dac.set_channel_vref(Channel::Ch0, 2.5);
+//! dac.set_channel_vref(Channel::Ch1, 4.096);
+//!
+//! dac.set_voltage(Channel::Ch0, 1.2).unwrap();
+//! dac.set_voltage(Channel::Ch1, 3.3).unwrap();
+//! ```
+//!
+//! ### Calibrate channel 0 of a MCP4821 device
+//!
+//! ```no_run
+//! use mcp49xx::{Calibration, Channel, Command, Mcp49xx};
+//! use linux_embedded_hal::SpidevDevice;
+//!
+//! let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+//! let mut dac = Mcp49xx::new_mcp4821(spi);
+//!
+//! // Codes 100 and 4000 were sent and the outputs measured were
+//! // equivalent to codes 103.5 and 4041.2.
+//! let cal = Calibration::from_measurements(100, 103.5, 4000, 4041.2).unwrap();
+//! dac.set_calibration(Channel::Ch0, cal);
+//!
+//! dac.send(Command::default().value(2048)).unwrap();
+//! ```
+//!
+//! ### Store the calibration in flash memory
+//!
+//! The `CalibrationData` can be serialized into any buffer. Additionally,
+//! it can be stored and loaded with the `embedded-storage` NOR flash traits
+//! when enabling the `storage` feature.
+//!
+//! ```ignore
+//! use mcp49xx::CalibrationData;
+//!
+//! dac.calibration_data().save(&mut flash, 0x1000).unwrap();
+//!
+//! // at boot
+//! let data = CalibrationData::load(&mut flash, 0x1000).unwrap();
+//! dac.set_calibration_data(data);
+//! ```
+//!
+//! ### Update both channels of a MCP4922 device at the same time
+//!
+//! ```no_run
+//! use mcp49xx::{Command, Mcp49xx};
+//! use linux_embedded_hal::{sysfs_gpio::Direction, Delay, SpidevDevice, SysfsPin};
+//!
+//! let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+//! let ldac = SysfsPin::new(25);
+//! ldac.export().unwrap();
+//! ldac.set_direction(Direction::Low).unwrap();
+//! let mut dac = Mcp49xx::new_mcp4922(spi).with_ldac(ldac);
+//!
+//! let ch0 = Command::default().value(1024);
+//! let ch1 = Command::default().value(3072);
+//! dac.send_synchronized(ch0, ch1, &mut Delay).unwrap();
+//!
+//! // Get LDAC pin and SPI device back
+//! let (dac, _ldac) = dac.release_ldac();
+//! let _spi = dac.destroy();
+//! ```
+//!
+//! ### Use each channel of a MCP4822 device independently
+//!
+//! ```no_run
+//! use mcp49xx::{Command, Mcp49xx, SharedMcp49xx};
+//! use linux_embedded_hal::SpidevDevice;
+//!
+//! let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+//! let mut shared = SharedMcp49xx::new();
+//! let (mut ch0, mut ch1) = Mcp49xx::new_mcp4822(spi).split(&mut shared);
+//!
+//! // The handles can be passed to different parts of the application
+//! ch0.send(Command::default().value(1024)).unwrap();
+//! ch1.send(Command::default().value(3072)).unwrap();
+//!
+//! // Get the driver back
+//! let dac = Mcp49xx::join(ch0, ch1);
+//! let _spi = dac.destroy();
+//! ```
+//!
+//! In order to use the handles from different threads or interrupt priorities,
+//! `SyncSharedMcp49xx` shares the driver through a `critical-section` mutex
+//! when enabling the `critical-section` feature.
+//!
+//! ### Generate a triangle waveform with a MCP4802 device
+//!
+//! ```no_run
+//! use mcp49xx::{marker::Resolution8Bit, waveform::Waveform, Channel, Command, Mcp49xx};
+//! use linux_embedded_hal::{Delay, SpidevDevice};
+//!
+//! let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+//! let mut dac = Mcp49xx::new_mcp4802(spi);
+//!
+//! // Between 10% and 90% of the full scale, 64 samples per period
+//! let triangle = Waveform::triangle().amplitude(0.4).offset(0.5);
+//! let samples = triangle.samples::<Resolution8Bit>(64);
+//! let cmd = Command::default().channel(Channel::Ch1);
+//! loop {
+//!     dac.send_samples(cmd, samples.clone(), &mut Delay, 100_000).unwrap();
+//! }
+//! ```
+//!
+//! ### Send commands asynchronously
+//!
+//! An asynchronous driver based on the `embedded-hal-async` traits is
+//! available in the `asynch` module when enabling the `async` feature.
+//!
+//! ```ignore
+//! use mcp49xx::{asynch::Mcp49xx, Channel, Command};
+//!
+//! let mut dac = Mcp49xx::new_mcp4822(spi);
+//! let cmd = Command::default().channel(Channel::Ch1).value(1024);
+//! dac.send(cmd).await.unwrap();
+//! ```
+//!
+//! ### Use the embedded-hal 0.2 interface
+//!
+//! The driver for `embedded-hal` 0.2 is available in the `eh02` module when
+//! enabling the `eh02` feature. It owns the chip select pin and takes a
+//! reference to the SPI bus on every call.
+//!
+//! ```toml
+//! [dependencies]
+//! mcp49xx = { version = "0.3", features = ["eh02"] }
+//! ```
+//!
+//! ```ignore
+//! use mcp49xx::{eh02::Mcp49xx, Channel, Command};
+//!
+//! let mut dac = Mcp49xx::new_mcp4921(chip_select);
+//! let cmd = Command::default().channel(Channel::Ch0).value(1024);
 //! dac.send(&mut spi, cmd).unwrap();
 //!
 //! // Get CS pin back
 //! let _chip_select = dac.destroy();
 //! ```
+//!
+//! ### Test without hardware
+//!
+//! A simulated device which models the registers and outputs of each device
+//! is available in the `sim` module when enabling the `sim` feature.
+//!
+//! ```ignore
+//! use mcp49xx::{sim::Simulator, Channel, Command, Mcp49xx};
+//!
+//! let sim = Simulator::new_mcp4921(2.5);
+//! let mut dac = Mcp49xx::new_mcp4921(sim.spi());
+//! dac.send(Command::default().value(2048)).unwrap();
+//! assert_eq!(sim.output_voltage(Channel::Ch0), Some(1.25));
+//! ```
 
 #![deny(unsafe_code, missing_docs)]
 #![no_std]
 
+use core::convert::Infallible;
 use core::marker::PhantomData;
+use embedded_hal::spi::{self, SpiDevice};
 pub use embedded_hal::spi::{MODE_0, MODE_3};
-use embedded_hal::{blocking::spi::Write, digital::v2::OutputPin};
 
 /// All possible errors in this crate
 #[derive(Debug, PartialEq)]
-pub enum Error<CommE, PinE> {
+pub enum Error<CommE, PinE = Infallible> {
     /// Communication error
     Comm(CommE),
     /// Pin error
@@ -158,6 +379,26 @@ pub enum Error<CommE, PinE> {
     InvalidValue,
     /// Buffering is not available in the current device (MCP48xx)
     BufferingNotSupported,
+    /// The reference voltage has not been set (MCP49xx)
+    NoReference,
+    /// The output voltage would exceed the supply voltage minus the headroom
+    /// when using double gain (2x)
+    VddHeadroomExceeded,
+    /// The buffer provided is too small for the frames
+    BufferTooSmall,
+}
+
+impl<CommE, PinE> spi::Error for Error<CommE, PinE>
+where
+    CommE: spi::Error,
+    PinE: core::fmt::Debug,
+{
+    fn kind(&self) -> spi::ErrorKind {
+        match self {
+            Error::Comm(e) => e.kind(),
+            _ => spi::ErrorKind::Other,
+        }
+    }
 }
 
 /// Channel selector
@@ -168,83 +409,218 @@ pub enum Channel {
     /// Channel 1 (only valid for dual devices. i.e. MCP4xx2)
     ///
     /// Sending a command on this channel to a single channel device will
-    /// return an `Error::InvalidChannel`.
+    /// return an `Error::InvalidChannel`. This can be checked at compile
+    /// time instead by using `send_to()` with [`Ch1`].
     Ch1,
 }
 
+impl Channel {
+    pub(crate) fn index(self) -> usize {
+        match self {
+            Channel::Ch0 => 0,
+            Channel::Ch1 => 1,
+        }
+    }
+}
+
+/// Power state of the device as set through the hardware SHDN pin
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PowerState {
+    /// SHDN pin is high. Channels operate according to the commands sent.
+    Active,
+    /// SHDN pin is low. All channels are shut down.
+    Shutdown,
+}
+
+/// Placeholder for an optional pin which is not managed by the driver
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct NoPin;
+
 /// MCP49xx digital potentiometer driver
 #[derive(Debug)]
-pub struct Mcp49xx<CS, SPI, RES, CH, BUF> {
-    cs: CS,
-    _spi: PhantomData<SPI>,
+pub struct Mcp49xx<SPI, RES, CH, BUF, LDAC = NoPin, SHDN = NoPin> {
+    spi: SPI,
+    ldac: LDAC,
+    shdn: SHDN,
+    state: State,
     _resolution: PhantomData<RES>,
     _channels: PhantomData<CH>,
     _buffering: PhantomData<BUF>,
 }
 
+// state tracked by the driver, independent of the pins attached
+#[derive(Debug, Default, Clone, Copy)]
+struct State {
+    power_state: Option<PowerState>,
+    shadow: [Option<Command>; 2],
+    vref: [Option<f32>; 2],
+    max_output: Option<f32>,
+    calibration: [Option<Calibration>; 2],
+    value_policy: ValuePolicy,
+}
+
 /// Markers
 pub mod marker {
     /// 12-Bit resolution device
+    #[derive(Debug, Clone, Copy, PartialEq)]
     pub struct Resolution12Bit(());
     /// 10-Bit resolution device
+    #[derive(Debug, Clone, Copy, PartialEq)]
     pub struct Resolution10Bit(());
     /// 8-Bit resolution device
+    #[derive(Debug, Clone, Copy, PartialEq)]
     pub struct Resolution8Bit(());
 
     /// Single channel device
+    #[derive(Debug, Clone, Copy, PartialEq)]
     pub struct SingleChannel(());
     /// Dual channel device
+    #[derive(Debug, Clone, Copy, PartialEq)]
     pub struct DualChannel(());
 
     /// Device supports buffered commands
+    #[derive(Debug, Clone, Copy, PartialEq)]
     pub struct Buffered(());
     /// Device does not support buffered commands
+    #[derive(Debug, Clone, Copy, PartialEq)]
     pub struct Unbuffered(());
 }
 
-impl<CS, SPI, RES, CH, BUF, CommE, PinE> Mcp49xx<CS, SPI, RES, CH, BUF>
+impl<SPI, RES, CH, BUF, LDAC, SHDN, CommE> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN>
 where
-    CS: OutputPin<Error = PinE>,
-    SPI: Write<u8, Error = CommE>,
-    RES: ResolutionSupport<CommE, PinE>,
-    CH: ChannelSupport<CommE, PinE>,
-    BUF: BufferingSupport<CommE, PinE>,
+    SPI: SpiDevice<u8, Error = CommE>,
+    RES: ResolutionSupport<CommE, Infallible>,
+    CH: ChannelSupport<CommE, Infallible>,
+    BUF: BufferingSupport<CommE, Infallible>,
 {
     /// Send command to device.
     ///
     /// This will return an error if the command is not appropriate for the current device:
     /// - If the channel is not available it will return `Error::InvalidChannel`.
-    /// - If the value is too big it will return `Error::InvalidValue`.
+    /// - If the value is too big it will return `Error::InvalidValue` (see `set_value_policy()`).
     /// - If buffering is not supported it will return `Error::BufferingNotSupported`.
     ///
     /// Otherwise if a communication error happened it will return `Error::Comm`.
-    pub fn send(&mut self, spi: &mut SPI, command: Command) -> Result<(), Error<CommE, PinE>> {
-        CH::check_channel_is_appropriate(command.channel)?;
-        RES::check_value_is_appropriate(command.value)?;
-        BUF::check_buffering_is_appropriate(command.buffered)?;
-        let value = RES::get_value_for_spi(command.value);
+    ///
+    /// If a calibration has been set for the channel, it is applied to the value.
+    ///
+    /// The command is stored as the current state of the channel if it was
+    /// sent successfully. After a communication error the state of the
+    /// channel is unknown.
+    pub fn send(&mut self, command: Command) -> Result<(), Error<CommE>> {
+        let (command, payload) = self
+            .state
+            .get_payload::<RES, CH, BUF, CommE, Infallible>(command)?;
+        self.write_payload(command, payload)
+    }
+
+    // send a command whose value is already a code, so the value policy does not apply
+    fn send_code(&mut self, command: Command) -> Result<(), Error<CommE>> {
+        let payload = self
+            .state
+            .get_code_payload::<RES, CH, BUF, CommE, Infallible>(command)?;
+        self.write_payload(command, payload)
+    }
 
-        self.cs.set_low().map_err(Error::Pin)?;
-        let payload: [u8; 2] = [command.get_config_bits() | value[0], value[1]];
-        let result = spi.write(&payload).map_err(Error::Comm);
-        self.cs.set_high().map_err(Error::Pin)?;
-        result
+    // write the frame of a checked command and track the state of the channel
+    fn write_payload(&mut self, command: Command, payload: [u8; 2]) -> Result<(), Error<CommE>> {
+        let index = command.channel.index();
+        self.state.shadow[index] = None;
+        self.spi.write(&payload).map_err(Error::Comm)?;
+        self.state.shadow[index] = Some(command);
+        Ok(())
+    }
+
+    /// Send command to a channel selected at compile time.
+    ///
+    /// The channel selected in the command is ignored.
+    /// Selecting channel 1 in a single channel device fails to compile:
+    ///
+    /// ```compile_fail
+    /// use linux_embedded_hal::SpidevDevice;
+    /// use mcp49xx::{Ch1, Command, Mcp49xx};
+    ///
+    /// let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+    /// let mut dac = Mcp49xx::new_mcp4921(spi);
+    /// dac.send_to(Ch1, Command::default()).unwrap();
+    /// ```
+    ///
+    /// Otherwise, this behaves like `send()`.
+    pub fn send_to<C: ChannelOf<CH>>(
+        &mut self,
+        _channel: C,
+        command: Command,
+    ) -> Result<(), Error<CommE>> {
+        self.send(command.channel(C::CHANNEL))
+    }
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN> {
+    /// Get the last command successfully sent to a channel.
+    ///
+    /// The device cannot be read so this is the state tracked by the driver.
+    /// Returns `None` if no command has been sent to the channel yet or if
+    /// an error happened while sending the last one, in which case the state
+    /// of the channel is unknown.
+    ///
+    /// The value is the code sent after applying the value policy and before
+    /// applying the calibration.
+    pub fn current(&self, channel: Channel) -> Option<Command> {
+        self.state.shadow[channel.index()]
     }
 }
 
+mod any;
+pub use crate::any::{AnyMcp49xx, DeviceKind};
+#[cfg(feature = "async")]
+pub mod asynch;
+mod calibration;
+mod code;
+pub use crate::code::{Code, CodeError};
 mod command;
 mod construction;
+pub mod dds;
+#[cfg(feature = "eh02")]
+pub mod eh02;
+mod frame;
+pub use crate::frame::{DecodedFrame, FrameError};
+mod ldac;
+mod normalized;
+pub use crate::normalized::Normalized;
+mod policy;
+pub use crate::policy::ValuePolicy;
+mod prepared;
+pub use crate::prepared::PreparedCommand;
+mod shutdown;
+#[cfg(feature = "sim")]
+pub mod sim;
+mod split;
+#[cfg(feature = "critical-section")]
+pub use crate::split::SyncSharedMcp49xx;
+pub use crate::split::{ChannelHandle, SharedDriver, SharedMcp49xx};
 
 mod resolution;
-pub use crate::command::Command;
+pub use crate::calibration::{Calibration, MAX_CALIBRATION_POINTS};
+pub use crate::command::{Command, DeviceCommand};
+pub use crate::resolution::Resolution;
 #[doc(hidden)]
 pub use crate::resolution::ResolutionSupport;
 mod channel;
 #[doc(hidden)]
 pub use crate::channel::ChannelSupport;
+pub use crate::channel::{Ch0, Ch1, ChannelOf};
 mod buffering;
 #[doc(hidden)]
 pub use crate::buffering::BufferingSupport;
+mod reference;
+#[doc(hidden)]
+pub use crate::reference::ReferenceSupport;
+mod storage;
+mod voltage;
+pub mod waveform;
+#[cfg(feature = "storage")]
+pub use crate::storage::StorageError;
+pub use crate::storage::{CalibrationData, CalibrationDataError, CALIBRATION_DATA_MAX_SIZE};
 
 mod private {
     use crate::marker;
@@ -257,6 +633,13 @@ mod private {
     impl Sealed for marker::SingleChannel {}
     impl Sealed for marker::DualChannel {}
 
+    impl Sealed for crate::Ch0 {}
+    impl Sealed for crate::Ch1 {}
+
     impl Sealed for marker::Buffered {}
     impl Sealed for marker::Unbuffered {}
+
+    impl<SPI, RES, BUF, LDAC, SHDN> Sealed for crate::SharedMcp49xx<SPI, RES, BUF, LDAC, SHDN> {}
+    #[cfg(feature = "critical-section")]
+    impl<SPI, RES, BUF, LDAC, SHDN> Sealed for crate::SyncSharedMcp49xx<SPI, RES, BUF, LDAC, SHDN> {}
 }
@@ -0,0 +1,174 @@
+use crate::{BufferingSupport, ChannelSupport, Code, Command, Error, Mcp49xx, ResolutionSupport};
+use core::convert::Infallible;
+use embedded_hal::spi::SpiDevice;
+
+/// Output value as a fraction of the full-scale range
+///
+/// The fraction is stored in 1.15 fixed point, where `0x8000` is 1.0, and
+/// it is converted to the closest code for the resolution of the device
+/// (rounding half up), where 1.0 is the maximum code. This way the same value
+/// can be sent to devices with any resolution.
+///
+/// ```
+/// use mcp49xx::{Code, Normalized};
+///
+/// let half = Normalized::from_f32(0.5).unwrap();
+/// assert_eq!(Code::<8>::from(half).get(), 128);
+/// assert_eq!(Code::<12>::from(half).get(), 2048);
+/// assert_eq!(Code::<12>::from(Normalized::ONE), Code::<12>::MAX);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Normalized(u16);
+
+/// 1.0 in 1.15 fixed point
+const ONE: u16 = 1 << 15;
+
+impl Normalized {
+    /// Lowest output (0.0)
+    pub const ZERO: Self = Normalized(0);
+
+    /// Full-scale output (1.0)
+    pub const ONE: Self = Normalized(ONE);
+
+    /// Create from a fraction in 1.15 fixed point (`0x8000` is 1.0).
+    ///
+    /// Returns `None` if the fraction is greater than 1.0.
+    pub const fn from_fraction(fraction: u16) -> Option<Self> {
+        if fraction <= ONE {
+            Some(Normalized(fraction))
+        } else {
+            None
+        }
+    }
+
+    /// Create from a fraction in 1.15 fixed point clamping it to 1.0.
+    pub const fn saturating_from_fraction(fraction: u16) -> Self {
+        if fraction <= ONE {
+            Normalized(fraction)
+        } else {
+            Self::ONE
+        }
+    }
+
+    /// Create from a fraction in the range `0.0..=1.0`.
+    ///
+    /// It is rounded to the closest 1.15 fixed point fraction.
+    /// Returns `None` if it is out of range or NaN.
+    pub fn from_f32(fraction: f32) -> Option<Self> {
+        // also rejects NaN
+        if !(0.0..=1.0).contains(&fraction) {
+            return None;
+        }
+        Some(Normalized((fraction * f32::from(ONE) + 0.5) as u16))
+    }
+
+    /// Create from a fraction clamping it to the range `0.0..=1.0`.
+    ///
+    /// NaN is converted to 0.0.
+    pub fn saturating_from_f32(fraction: f32) -> Self {
+        // NaN is converted to 0
+        Normalized((fraction.clamp(0.0, 1.0) * f32::from(ONE) + 0.5) as u16)
+    }
+
+    /// Create from the code of a resolution, where the maximum code is 1.0.
+    pub const fn from_code<const BITS: u8>(code: Code<BITS>) -> Self {
+        let max = Code::<BITS>::MAX.get() as u32;
+        Normalized(((code.get() as u32 * ONE as u32 + max / 2) / max) as u16)
+    }
+
+    /// Get the fraction in 1.15 fixed point (`0x8000` is 1.0)
+    pub const fn fraction(self) -> u16 {
+        self.0
+    }
+
+    /// Get the fraction
+    pub fn to_f32(self) -> f32 {
+        f32::from(self.0) / f32::from(ONE)
+    }
+
+    /// Get the closest code for a resolution in bits
+    pub(crate) const fn code(self, bits: u8) -> u16 {
+        let max = (1_u32 << bits) - 1;
+        ((self.0 as u32 * max + (ONE as u32 >> 1)) >> 15) as u16
+    }
+}
+
+impl<const BITS: u8> From<Normalized> for Code<BITS> {
+    fn from(value: Normalized) -> Self {
+        Code::wrapping(value.code(BITS))
+    }
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN, CommE> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN>
+where
+    SPI: SpiDevice<u8, Error = CommE>,
+    RES: ResolutionSupport<CommE, Infallible>,
+    CH: ChannelSupport<CommE, Infallible>,
+    BUF: BufferingSupport<CommE, Infallible>,
+{
+    /// Send a command setting the value to the closest code to a fraction
+    /// of the full-scale range.
+    ///
+    /// The value in the command is ignored and the value policy does not
+    /// apply. Otherwise, the same errors as in `send()` can be returned.
+    pub fn send_normalized(
+        &mut self,
+        command: Command,
+        value: Normalized,
+    ) -> Result<(), Error<CommE>> {
+        self.send_code(command.value(value.code(RES::BITS)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_range() {
+        assert_eq!(Normalized::from_fraction(0x8000), Some(Normalized::ONE));
+        assert_eq!(Normalized::from_fraction(0x8001), None);
+        assert_eq!(
+            Normalized::saturating_from_fraction(0xFFFF),
+            Normalized::ONE
+        );
+        assert_eq!(Normalized::from_f32(1.5), None);
+        assert_eq!(Normalized::from_f32(-0.1), None);
+        assert_eq!(Normalized::from_f32(f32::NAN), None);
+        assert_eq!(Normalized::saturating_from_f32(1.5), Normalized::ONE);
+        assert_eq!(Normalized::saturating_from_f32(-1.0), Normalized::ZERO);
+        assert_eq!(Normalized::saturating_from_f32(f32::NAN), Normalized::ZERO);
+    }
+
+    #[test]
+    fn converts_f32() {
+        assert_eq!(Normalized::from_f32(0.25).unwrap().fraction(), 0x2000);
+        assert_eq!(Normalized::from_f32(1.0).unwrap(), Normalized::ONE);
+        assert_eq!(Normalized::from_fraction(0x6000).unwrap().to_f32(), 0.75);
+    }
+
+    #[test]
+    fn rounds_to_closest_code() {
+        assert_eq!(Normalized::ZERO.code(8), 0);
+        assert_eq!(Normalized::ONE.code(8), 255);
+        assert_eq!(Normalized::ONE.code(10), 1023);
+        assert_eq!(Normalized::ONE.code(12), 4095);
+        // 0.25 is code 63.75 in 8 bits, 255.75 in 10 bits and 1023.75 in 12 bits
+        let quarter = Normalized::from_f32(0.25).unwrap();
+        assert_eq!(quarter.code(8), 64);
+        assert_eq!(quarter.code(10), 256);
+        assert_eq!(quarter.code(12), 1024);
+    }
+
+    #[test]
+    fn roundtrips_codes() {
+        for code in 0..=1023 {
+            let code = Code::<10>::new(code).unwrap();
+            assert_eq!(Code::<10>::from(Normalized::from_code(code)), code);
+        }
+        for code in 0..=4095 {
+            let code = Code::<12>::new(code).unwrap();
+            assert_eq!(Code::<12>::from(Normalized::from_code(code)), code);
+        }
+    }
+}
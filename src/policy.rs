@@ -0,0 +1,100 @@
+use crate::{Mcp49xx, Normalized};
+
+/// Handling of the values of the commands sent
+///
+/// The policy converts the value of a command to the code sent to the device.
+/// It is applied before checking the command and applying the calibration.
+/// Codes are not affected by it: values computed by the driver like in
+/// `send_voltage()` or `send_normalized()`, typed codes set with
+/// `DeviceCommand::code()` and sent with `send_command()`, samples sent with
+/// `send_samples()` and samples of a `Dds` sent with `send_dds()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValuePolicy {
+    /// The value is the code. Values too big for the resolution are rejected
+    /// with `Error::InvalidValue` (default).
+    Reject,
+    /// The value is the code. Values too big for the resolution are clamped
+    /// to the maximum code.
+    Saturate,
+    /// The value is a full-scale 16-bit value. The low bits not supported by
+    /// the resolution are dropped (e.g. `0xFFFF` is code 4095 in a 12-bit device).
+    Scale16Bit,
+    /// The value is a fraction of the full-scale range in 1.15 fixed point,
+    /// where `0x8000` is 1.0 (the maximum code). It is converted like a
+    /// [`Normalized`] value. Values above `0x8000` are rejected with
+    /// `Error::InvalidValue`.
+    ScaleNormalized,
+}
+
+impl Default for ValuePolicy {
+    fn default() -> Self {
+        ValuePolicy::Reject
+    }
+}
+
+impl ValuePolicy {
+    // Get the code for a value. Returns `None` if the value must be rejected.
+    // Out of range values are left for the command check to reject.
+    pub(crate) fn apply(self, value: u16, bits: u8) -> Option<u16> {
+        let max = ((1_u32 << bits) - 1) as u16;
+        match self {
+            ValuePolicy::Reject => Some(value),
+            ValuePolicy::Saturate => Some(value.min(max)),
+            ValuePolicy::Scale16Bit => Some(value >> (16 - bits)),
+            ValuePolicy::ScaleNormalized => {
+                Normalized::from_fraction(value).map(|value| value.code(bits))
+            }
+        }
+    }
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN> {
+    /// Set how the values of the commands sent are converted to codes.
+    ///
+    /// This affects all commands sent afterwards, including the ones sent
+    /// through `prepare()` or `encode()`.
+    /// See [`ValuePolicy`].
+    pub fn set_value_policy(&mut self, policy: ValuePolicy) {
+        self.state.value_policy = policy;
+    }
+
+    /// Get how the values of the commands sent are converted to codes.
+    pub fn value_policy(&self) -> ValuePolicy {
+        self.state.value_policy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_keeps_value() {
+        assert_eq!(ValuePolicy::default(), ValuePolicy::Reject);
+        assert_eq!(ValuePolicy::Reject.apply(300, 8), Some(300));
+    }
+
+    #[test]
+    fn saturates_to_max_code() {
+        assert_eq!(ValuePolicy::Saturate.apply(200, 8), Some(200));
+        assert_eq!(ValuePolicy::Saturate.apply(300, 8), Some(255));
+        assert_eq!(ValuePolicy::Saturate.apply(0xFFFF, 12), Some(4095));
+    }
+
+    #[test]
+    fn scales_16_bit_values() {
+        assert_eq!(ValuePolicy::Scale16Bit.apply(0xFFFF, 12), Some(4095));
+        assert_eq!(ValuePolicy::Scale16Bit.apply(0x8000, 10), Some(512));
+        assert_eq!(ValuePolicy::Scale16Bit.apply(0x00FF, 8), Some(0));
+    }
+
+    #[test]
+    fn scales_normalized_values() {
+        let policy = ValuePolicy::ScaleNormalized;
+        assert_eq!(policy.apply(0, 12), Some(0));
+        assert_eq!(policy.apply(0x4000, 12), Some(2048));
+        assert_eq!(policy.apply(0x8000, 12), Some(4095));
+        assert_eq!(policy.apply(0x8000, 8), Some(255));
+        assert_eq!(policy.apply(0x8001, 8), None);
+    }
+}
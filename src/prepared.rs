@@ -0,0 +1,73 @@
+use crate::{BufferingSupport, ChannelSupport, Command, Error, Mcp49xx, ResolutionSupport};
+use core::convert::Infallible;
+use core::marker::PhantomData;
+use embedded_hal::spi::SpiDevice;
+
+/// Command which has been checked and encoded for a device type
+///
+/// It can only be sent to devices with the same resolution, number of
+/// channels and buffering support with `send_prepared()`, which just writes
+/// the frame. Created with `prepare()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreparedCommand<RES, CH, BUF> {
+    command: Command,
+    frame: [u8; 2],
+    _resolution: PhantomData<RES>,
+    _channels: PhantomData<CH>,
+    _buffering: PhantomData<BUF>,
+}
+
+impl<RES, CH, BUF> PreparedCommand<RES, CH, BUF> {
+    /// Get the command
+    ///
+    /// The value is the code after applying the value policy, as in `current()`.
+    pub fn command(&self) -> Command {
+        self.command
+    }
+
+    /// Get the frame which will be sent
+    pub fn frame(&self) -> [u8; 2] {
+        self.frame
+    }
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN, CommE> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN>
+where
+    SPI: SpiDevice<u8, Error = CommE>,
+    RES: ResolutionSupport<CommE, Infallible>,
+    CH: ChannelSupport<CommE, Infallible>,
+    BUF: BufferingSupport<CommE, Infallible>,
+{
+    /// Check and encode a command so that it can be sent repeatedly with
+    /// `send_prepared()` without further processing.
+    ///
+    /// The same errors as in `send()` can be returned.
+    ///
+    /// The value policy and the calibration of the channel are applied now.
+    /// Later changes to them do not affect the prepared command.
+    pub fn prepare(&self, command: Command) -> Result<PreparedCommand<RES, CH, BUF>, Error<CommE>> {
+        let (command, frame) = self
+            .state
+            .get_payload::<RES, CH, BUF, CommE, Infallible>(command)?;
+        Ok(PreparedCommand {
+            command,
+            frame,
+            _resolution: PhantomData,
+            _channels: PhantomData,
+            _buffering: PhantomData,
+        })
+    }
+
+    /// Send a prepared command to the device.
+    ///
+    /// The command was already checked in `prepare()` so only a
+    /// communication error can be returned.
+    ///
+    /// The command is stored as the current state of the channel as in `send()`.
+    pub fn send_prepared(
+        &mut self,
+        command: &PreparedCommand<RES, CH, BUF>,
+    ) -> Result<(), Error<CommE>> {
+        self.write_payload(command.command, command.frame)
+    }
+}
@@ -0,0 +1,19 @@
+use crate::{marker, private};
+
+/// Internal voltage reference of the MCP48xx devices
+pub(crate) const INTERNAL_VREF: f32 = 2.048;
+
+#[doc(hidden)]
+pub trait ReferenceSupport: private::Sealed {
+    const INTERNAL_VREF: Option<f32>;
+}
+
+// The MCP49xx devices (the ones supporting buffering) use an external reference
+impl ReferenceSupport for marker::Buffered {
+    const INTERNAL_VREF: Option<f32> = None;
+}
+
+// The MCP48xx devices (the ones not supporting buffering) use an internal reference
+impl ReferenceSupport for marker::Unbuffered {
+    const INTERNAL_VREF: Option<f32> = Some(INTERNAL_VREF);
+}
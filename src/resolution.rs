@@ -1,7 +1,33 @@
-use crate::{marker, private, Error};
+use crate::{marker, private, Code, Error, Normalized};
+
+/// Resolution of a device
+///
+/// This is implemented by the `marker::Resolution*` types.
+pub trait Resolution: private::Sealed {
+    /// Number of bits of the values
+    const BITS: u8;
+
+    /// DAC code with the number of bits of the resolution
+    type Code: Copy + Into<u16> + From<Normalized>;
+}
+
+impl Resolution for marker::Resolution12Bit {
+    const BITS: u8 = 12;
+    type Code = Code<12>;
+}
+
+impl Resolution for marker::Resolution10Bit {
+    const BITS: u8 = 10;
+    type Code = Code<10>;
+}
+
+impl Resolution for marker::Resolution8Bit {
+    const BITS: u8 = 8;
+    type Code = Code<8>;
+}
 
 #[doc(hidden)]
-pub trait ResolutionSupport<CommE, PinE>: private::Sealed {
+pub trait ResolutionSupport<CommE, PinE>: Resolution {
     fn check_value_is_appropriate(value: u16) -> Result<(), Error<CommE, PinE>>;
     fn get_value_for_spi(value: u16) -> [u8; 2];
 }
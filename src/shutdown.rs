@@ -0,0 +1,88 @@
+use crate::{Error, Mcp49xx, NoPin, PowerState, State};
+use core::marker::PhantomData;
+use embedded_hal::{digital::OutputPin, spi::ErrorType};
+
+impl<SPI, RES, CH, BUF, LDAC> Mcp49xx<SPI, RES, CH, BUF, LDAC, NoPin> {
+    /// Attach the SHDN output pin to the driver.
+    ///
+    /// The hardware SHDN pin is available in the MCP48x1 and MCP49x2 devices.
+    ///
+    /// The pin state is not changed so the power state is unknown until
+    /// `hardware_shutdown()` or `hardware_wake()` is called.
+    pub fn with_shdn<SHDN>(self, shdn: SHDN) -> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN> {
+        Mcp49xx {
+            spi: self.spi,
+            ldac: self.ldac,
+            shdn,
+            state: State {
+                power_state: None,
+                ..self.state
+            },
+            _resolution: PhantomData,
+            _channels: PhantomData,
+            _buffering: PhantomData,
+        }
+    }
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN> {
+    /// Detach the SHDN output pin from the driver and return it.
+    pub fn release_shdn(self) -> (Mcp49xx<SPI, RES, CH, BUF, LDAC, NoPin>, SHDN) {
+        let dev = Mcp49xx {
+            spi: self.spi,
+            ldac: self.ldac,
+            shdn: NoPin,
+            state: State {
+                power_state: None,
+                ..self.state
+            },
+            _resolution: PhantomData,
+            _channels: PhantomData,
+            _buffering: PhantomData,
+        };
+        (dev, self.shdn)
+    }
+
+    /// Get the power state last set through the SHDN pin.
+    ///
+    /// Returns `None` if no SHDN pin is attached or its state has not been
+    /// set yet or the last attempt to set it failed.
+    pub fn power_state(&self) -> Option<PowerState> {
+        self.state.power_state
+    }
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN, CommE, PinE> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN>
+where
+    SPI: ErrorType<Error = CommE>,
+    SHDN: OutputPin<Error = PinE>,
+{
+    /// Shut down all channels by setting the SHDN pin low.
+    ///
+    /// The outputs present a high resistance load while the device is shut
+    /// down. The register contents are kept and commands can still be sent.
+    /// They will take effect after `hardware_wake()`.
+    pub fn hardware_shutdown(&mut self) -> Result<(), Error<CommE, PinE>> {
+        self.set_power_state(PowerState::Shutdown)
+    }
+
+    /// Wake up the device by setting the SHDN pin high.
+    ///
+    /// The software shutdown bit of each channel still applies, so a channel
+    /// whose last command was sent with `Command::shutdown()` stays shut down
+    /// until a command enabling it is sent.
+    pub fn hardware_wake(&mut self) -> Result<(), Error<CommE, PinE>> {
+        self.set_power_state(PowerState::Active)
+    }
+
+    fn set_power_state(&mut self, state: PowerState) -> Result<(), Error<CommE, PinE>> {
+        self.state.power_state = None;
+        match state {
+            PowerState::Shutdown => self.shdn.set_low(),
+            PowerState::Active => self.shdn.set_high(),
+        }
+        .map_err(Error::Pin)?;
+        self.state.power_state = Some(state);
+        Ok(())
+    }
+}
@@ -0,0 +1,544 @@
+//! Simulated device for testing without hardware.
+//!
+//! A [`Simulator`] models the registers and the output of a device and
+//! provides SPI and pin handles which can be passed to a driver:
+//! - [`spi()`](Simulator::spi) implements `SpiDevice`, including the chip select.
+//! - [`spi_bus()`](Simulator::spi_bus) implements `SpiBus` and is used together
+//!   with the chip select pin handle from [`cs()`](Simulator::cs).
+//! - [`ldac()`](Simulator::ldac) and [`shdn()`](Simulator::shdn) implement `OutputPin`.
+//!   The SHDN pin is only available in the devices that have it (MCP48x1 and MCP49x2).
+//!
+//! The handles implement the `embedded-hal` 0.2 traits as well if the `eh02`
+//! feature is enabled and the `embedded-hal-async` `SpiDevice` trait if the
+//! `async` feature is enabled.
+//!
+//! The frames are decoded like the device does when the chip select line
+//! goes high, writing the input register of a channel. Frames with a length
+//! other than 16 bits are ignored.
+//! The input register is transferred to the DAC register, which determines
+//! the output, at the same time if LDAC is low or otherwise when LDAC goes
+//! low. LDAC and SHDN are initially low and high respectively, as if they
+//! were not connected to the microcontroller.
+//! Initially all channels are shut down with value 0.
+//!
+//! Besides the ideal output voltage, an analog model of the outputs driven
+//! by a virtual clock is provided. Once the clock is advanced with
+//! [`advance()`](Simulator::advance) or the delay handle from
+//! [`delay()`](Simulator::delay), the outputs move towards their targets
+//! according to the [`AnalogParameters`], which include the slew rate,
+//! settling time, INL/DNL and shutdown output resistance.
+//!
+//! It is available when enabling the `sim` feature.
+//!
+//! ```
+//! use mcp49xx::{sim::Simulator, Channel, Command, Mcp49xx};
+//!
+//! let sim = Simulator::new_mcp4922(2.5);
+//! let mut dac = Mcp49xx::new_mcp4922(sim.spi());
+//! dac.send(Command::default().channel(Channel::Ch1).value(2048)).unwrap();
+//! assert_eq!(sim.output_voltage(Channel::Ch0), None);
+//! assert_eq!(sim.output_voltage(Channel::Ch1), Some(1.25));
+//! ```
+
+use crate::reference::INTERNAL_VREF;
+use crate::{Channel, Command};
+use core::cell::RefCell;
+use core::convert::Infallible;
+use embedded_hal::{delay::DelayNs, digital, spi};
+
+mod analog;
+pub use self::analog::AnalogParameters;
+
+#[derive(Debug)]
+struct Device {
+    bits: u8,
+    dual: bool,
+    buffered: bool,
+    vref: [f32; 2],
+    input: [Command; 2],
+    dac: [Command; 2],
+    frame: u32,
+    frame_bits: u32,
+    cs_low: bool,
+    ldac_low: bool,
+    shdn_low: bool,
+    parameters: AnalogParameters,
+    load: [Option<(f32, f32)>; 2],
+    analog: [f32; 2],
+    now_ns: u64,
+}
+
+impl Device {
+    fn select(&mut self) {
+        if !self.cs_low {
+            self.cs_low = true;
+            self.frame = 0;
+            self.frame_bits = 0;
+        }
+    }
+
+    fn shift(&mut self, byte: u8) {
+        if self.cs_low {
+            self.frame = (self.frame << 8) | u32::from(byte);
+            self.frame_bits += 8;
+        }
+    }
+
+    fn deselect(&mut self) {
+        if !self.cs_low {
+            return;
+        }
+        self.cs_low = false;
+        if self.frame_bits != 16 {
+            return;
+        }
+        let frame = self.frame as u16;
+        let mut command = Command::from_config_bits((frame >> 8) as u8)
+            .value((frame & 0x0FFF) >> (12 - self.bits));
+        if !self.dual {
+            // Bit 15 set means the command is ignored in single channel devices.
+            if frame & 0x8000 != 0 {
+                return;
+            }
+        }
+        if !self.buffered {
+            // Don't care bit
+            command = command.unbuffered();
+        }
+        let index = command.channel.index();
+        self.input[index] = command;
+        if self.ldac_low {
+            self.dac[index] = command;
+        }
+    }
+
+    fn set_ldac(&mut self, low: bool) {
+        if low && !self.ldac_low {
+            self.dac = self.input;
+        }
+        self.ldac_low = low;
+    }
+
+    fn is_shutdown(&self, index: usize) -> bool {
+        self.shdn_low || self.dac[index].is_shutdown() || (index == 1 && !self.dual)
+    }
+
+    // output voltage if the channel is active, without settling
+    fn target_voltage(&self, index: usize, with_errors: bool) -> f32 {
+        let dac = self.dac[index];
+        let gain = if dac.is_double_gain() { 2.0 } else { 1.0 };
+        let steps = (1u32 << self.bits) as f32;
+        let mut code = f32::from(dac.value);
+        if with_errors {
+            code = (code + self.parameters.code_error(dac.value, self.bits)).max(0.0);
+        }
+        self.vref[index] * gain * code / steps
+    }
+
+    // output voltage if the channel is shut down
+    fn shutdown_voltage(&self, index: usize) -> f32 {
+        match self.load[index] {
+            Some((voltage, resistance)) => {
+                let shutdown_resistance = self.parameters.shutdown_resistance;
+                voltage * shutdown_resistance / (shutdown_resistance + resistance)
+            }
+            None => 0.0,
+        }
+    }
+
+    fn analog_voltage(&self, index: usize) -> f32 {
+        if self.is_shutdown(index) {
+            self.shutdown_voltage(index)
+        } else {
+            self.analog[index]
+        }
+    }
+
+    fn advance(&mut self, time_ns: u64) {
+        for index in 0..2 {
+            self.analog[index] = if self.is_shutdown(index) {
+                self.shutdown_voltage(index)
+            } else {
+                let target = self.target_voltage(index, true);
+                self.parameters
+                    .evolve(self.analog[index], target, time_ns, self.bits)
+            };
+        }
+        self.now_ns += time_ns;
+    }
+}
+
+/// Simulated device
+///
+/// See the [module documentation](self).
+#[derive(Debug)]
+pub struct Simulator {
+    device: RefCell<Device>,
+}
+
+impl Simulator {
+    fn new(bits: u8, dual: bool, buffered: bool, vref: f32) -> Self {
+        let off = Command::default().shutdown();
+        Simulator {
+            device: RefCell::new(Device {
+                bits,
+                dual,
+                buffered,
+                vref: [vref; 2],
+                input: [off, off.channel(Channel::Ch1)],
+                dac: [off, off.channel(Channel::Ch1)],
+                frame: 0,
+                frame_bits: 0,
+                cs_low: false,
+                ldac_low: true,
+                shdn_low: false,
+                parameters: AnalogParameters::typical(bits),
+                load: [None; 2],
+                analog: [0.0; 2],
+                now_ns: 0,
+            }),
+        }
+    }
+
+    /// Get an `SpiDevice` handle, which drives the chip select line itself.
+    pub fn spi(&self) -> SimSpi<'_> {
+        SimSpi { sim: self }
+    }
+
+    /// Get an `SpiBus` handle, to be used together with the chip select pin.
+    pub fn spi_bus(&self) -> SimSpiBus<'_> {
+        SimSpiBus { sim: self }
+    }
+
+    /// Get a handle to the chip select pin.
+    pub fn cs(&self) -> SimPin<'_> {
+        SimPin {
+            sim: self,
+            pin: Pin::Cs,
+        }
+    }
+
+    /// Get a handle to the LDAC pin.
+    pub fn ldac(&self) -> SimPin<'_> {
+        SimPin {
+            sim: self,
+            pin: Pin::Ldac,
+        }
+    }
+
+    /// Get a handle to the SHDN pin.
+    ///
+    /// Returns `None` if the device does not have the pin. It is only
+    /// available in the MCP48x1 and MCP49x2 devices.
+    pub fn shdn(&self) -> Option<SimPin<'_>> {
+        let device = self.device.borrow();
+        // MCP48x1: single channel without buffering, MCP49x2: dual channel with buffering
+        if device.dual != device.buffered {
+            return None;
+        }
+        Some(SimPin {
+            sim: self,
+            pin: Pin::Shdn,
+        })
+    }
+
+    /// Set the voltage applied to the reference input of a channel.
+    ///
+    /// MCP48xx devices use an internal 2.048V reference but it can be
+    /// changed here too.
+    pub fn set_vref(&self, channel: Channel, vref: f32) {
+        self.device.borrow_mut().vref[channel.index()] = vref;
+    }
+
+    /// Get the content of the input register of a channel.
+    ///
+    /// The value is the raw code sent.
+    pub fn input_register(&self, channel: Channel) -> Command {
+        self.device.borrow().input[channel.index()]
+    }
+
+    /// Get the content of the DAC register of a channel.
+    ///
+    /// The value is the raw code sent.
+    pub fn dac_register(&self, channel: Channel) -> Command {
+        self.device.borrow().dac[channel.index()]
+    }
+
+    /// Get the ideal output voltage of a channel.
+    ///
+    /// This is the voltage corresponding to the DAC register, without any
+    /// error or settling time.
+    /// Returns `None` if the channel is shut down (high impedance) or not available.
+    pub fn output_voltage(&self, channel: Channel) -> Option<f32> {
+        let device = self.device.borrow();
+        let index = channel.index();
+        if device.is_shutdown(index) {
+            return None;
+        }
+        Some(device.target_voltage(index, false))
+    }
+
+    /// Get the analog output voltage of a channel at the current time.
+    ///
+    /// While the channel is shut down, this is the voltage set by the load
+    /// and the shutdown output resistance (0V without load).
+    pub fn analog_voltage(&self, channel: Channel) -> f32 {
+        self.device.borrow().analog_voltage(channel.index())
+    }
+
+    /// Get the analog parameters of the outputs.
+    pub fn analog_parameters(&self) -> AnalogParameters {
+        self.device.borrow().parameters
+    }
+
+    /// Set the analog parameters of the outputs.
+    pub fn set_analog_parameters(&self, parameters: AnalogParameters) {
+        self.device.borrow_mut().parameters = parameters;
+    }
+
+    /// Connect a load to the output of a channel.
+    ///
+    /// The load is a voltage source with a series resistance (Ω) and only
+    /// affects the output while the channel is shut down.
+    /// Initially the outputs are not connected.
+    pub fn set_load(&self, channel: Channel, voltage: f32, resistance: f32) {
+        self.device.borrow_mut().load[channel.index()] = Some((voltage, resistance));
+    }
+
+    /// Get the time of the virtual clock (ns).
+    pub fn now_ns(&self) -> u64 {
+        self.device.borrow().now_ns
+    }
+
+    /// Advance the virtual clock, updating the analog outputs.
+    pub fn advance(&self, time_ns: u64) {
+        self.device.borrow_mut().advance(time_ns);
+    }
+
+    /// Get a `DelayNs` handle which advances the virtual clock.
+    pub fn delay(&self) -> SimDelay<'_> {
+        SimDelay { sim: self }
+    }
+
+    /// Record the analog output voltage of a channel, advancing the virtual
+    /// clock by a period after each sample.
+    pub fn record(&self, channel: Channel, period_ns: u32, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            *sample = self.analog_voltage(channel);
+            self.advance(u64::from(period_ns));
+        }
+    }
+
+    fn write(&self, bytes: &[u8]) {
+        let mut device = self.device.borrow_mut();
+        for byte in bytes {
+            device.shift(*byte);
+        }
+    }
+
+    fn transaction(&self, operations: &mut [spi::Operation<'_, u8>]) {
+        self.device.borrow_mut().select();
+        for operation in operations {
+            match operation {
+                spi::Operation::Write(bytes) => self.write(bytes),
+                spi::Operation::Transfer(read, write) => {
+                    self.write(write);
+                    read.fill(0);
+                }
+                spi::Operation::TransferInPlace(bytes) | spi::Operation::Read(bytes) => {
+                    self.write(bytes);
+                    bytes.fill(0);
+                }
+                spi::Operation::DelayNs(_) => (),
+            }
+        }
+        self.device.borrow_mut().deselect();
+    }
+}
+
+macro_rules! impl_sim_create {
+    ($dev:expr, $create:ident, $bits:expr, $dual:expr) => {
+        impl Simulator {
+            #[doc = concat!("Create a simulated ", $dev, " device.")]
+            ///
+            /// The internal 2.048V reference is used.
+            pub fn $create() -> Self {
+                Simulator::new($bits, $dual, false, INTERNAL_VREF)
+            }
+        }
+    };
+    ($dev:expr, $create:ident, $bits:expr, $dual:expr, vref) => {
+        impl Simulator {
+            #[doc = concat!("Create a simulated ", $dev, " device.")]
+            ///
+            /// The reference voltage is applied to all channels.
+            pub fn $create(vref: f32) -> Self {
+                Simulator::new($bits, $dual, true, vref)
+            }
+        }
+    };
+}
+
+impl_sim_create!("MCP4801", new_mcp4801, 8, false);
+impl_sim_create!("MCP4802", new_mcp4802, 8, true);
+impl_sim_create!("MCP4811", new_mcp4811, 10, false);
+impl_sim_create!("MCP4812", new_mcp4812, 10, true);
+impl_sim_create!("MCP4821", new_mcp4821, 12, false);
+impl_sim_create!("MCP4822", new_mcp4822, 12, true);
+impl_sim_create!("MCP4901", new_mcp4901, 8, false, vref);
+impl_sim_create!("MCP4902", new_mcp4902, 8, true, vref);
+impl_sim_create!("MCP4911", new_mcp4911, 10, false, vref);
+impl_sim_create!("MCP4912", new_mcp4912, 10, true, vref);
+impl_sim_create!("MCP4921", new_mcp4921, 12, false, vref);
+impl_sim_create!("MCP4922", new_mcp4922, 12, true, vref);
+
+/// `SpiDevice` handle of a [`Simulator`]
+#[derive(Debug, Clone, Copy)]
+pub struct SimSpi<'a> {
+    sim: &'a Simulator,
+}
+
+impl spi::ErrorType for SimSpi<'_> {
+    type Error = Infallible;
+}
+
+impl spi::SpiDevice<u8> for SimSpi<'_> {
+    fn transaction(&mut self, operations: &mut [spi::Operation<'_, u8>]) -> Result<(), Infallible> {
+        self.sim.transaction(operations);
+        Ok(())
+    }
+}
+
+/// `SpiBus` handle of a [`Simulator`]
+///
+/// The chip select line is driven with the pin handle from [`Simulator::cs()`].
+#[derive(Debug, Clone, Copy)]
+pub struct SimSpiBus<'a> {
+    sim: &'a Simulator,
+}
+
+impl spi::ErrorType for SimSpiBus<'_> {
+    type Error = Infallible;
+}
+
+impl spi::SpiBus<u8> for SimSpiBus<'_> {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Infallible> {
+        self.sim.write(words);
+        words.fill(0);
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Infallible> {
+        self.sim.write(words);
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Infallible> {
+        self.sim.write(write);
+        read.fill(0);
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Infallible> {
+        self.read(words)
+    }
+
+    fn flush(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// `DelayNs` handle of a [`Simulator`], which advances the virtual clock
+#[derive(Debug, Clone, Copy)]
+pub struct SimDelay<'a> {
+    sim: &'a Simulator,
+}
+
+impl DelayNs for SimDelay<'_> {
+    fn delay_ns(&mut self, ns: u32) {
+        self.sim.advance(u64::from(ns));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pin {
+    Cs,
+    Ldac,
+    Shdn,
+}
+
+/// Pin handle of a [`Simulator`]
+#[derive(Debug, Clone, Copy)]
+pub struct SimPin<'a> {
+    sim: &'a Simulator,
+    pin: Pin,
+}
+
+impl SimPin<'_> {
+    fn set(&self, low: bool) {
+        let mut device = self.sim.device.borrow_mut();
+        match (self.pin, low) {
+            (Pin::Cs, true) => device.select(),
+            (Pin::Cs, false) => device.deselect(),
+            (Pin::Ldac, low) => device.set_ldac(low),
+            (Pin::Shdn, low) => device.shdn_low = low,
+        }
+    }
+}
+
+impl digital::ErrorType for SimPin<'_> {
+    type Error = Infallible;
+}
+
+impl digital::OutputPin for SimPin<'_> {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.set(true);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.set(false);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "eh02")]
+mod eh02 {
+    use super::{SimPin, SimSpiBus};
+    use core::convert::Infallible;
+    use embedded_hal_0_2::{blocking::spi::Write, digital::v2::OutputPin};
+
+    impl Write<u8> for SimSpiBus<'_> {
+        type Error = Infallible;
+
+        fn write(&mut self, words: &[u8]) -> Result<(), Infallible> {
+            self.sim.write(words);
+            Ok(())
+        }
+    }
+
+    impl OutputPin for SimPin<'_> {
+        type Error = Infallible;
+
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.set(true);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.set(false);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::spi::SpiDevice<u8> for SimSpi<'_> {
+    async fn transaction(
+        &mut self,
+        operations: &mut [spi::Operation<'_, u8>],
+    ) -> Result<(), Infallible> {
+        self.sim.transaction(operations);
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+/// Analog characteristics of the outputs of a simulated device
+///
+/// The default values for each device are the typical values in the datasheet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnalogParameters {
+    /// Maximum rate of change of the output (V/µs)
+    pub slew_rate: f32,
+    /// Time for the error to decay from the full scale to 1/2 LSB once the
+    /// output is no longer slew rate limited (ns)
+    pub settling_time_ns: u32,
+    /// Integral nonlinearity at mid-scale (LSB)
+    ///
+    /// The error follows a parabolic bow which is zero at both ends of the range.
+    pub inl: f32,
+    /// Differential nonlinearity (LSB)
+    ///
+    /// Odd and even codes are displaced in opposite directions by half of it.
+    pub dnl: f32,
+    /// Resistance from the output to ground in shutdown (Ω)
+    pub shutdown_resistance: f32,
+}
+
+impl AnalogParameters {
+    pub(crate) fn typical(bits: u8) -> Self {
+        let (inl, dnl) = match bits {
+            12 => (2.0, 0.2),
+            10 => (0.5, 0.1),
+            _ => (0.125, 0.05),
+        };
+        AnalogParameters {
+            slew_rate: 0.55,
+            settling_time_ns: 4500,
+            inl,
+            dnl,
+            shutdown_resistance: 500_000.0,
+        }
+    }
+
+    // get the error of a code in LSB
+    pub(crate) fn code_error(&self, code: u16, bits: u8) -> f32 {
+        let max = ((1u32 << bits) - 1) as f32;
+        let x = f32::from(code) / max;
+        let dnl = if code & 1 == 0 { -0.5 } else { 0.5 } * self.dnl;
+        self.inl * 4.0 * x * (1.0 - x) + dnl
+    }
+
+    // get the output voltage after some time moving towards the target
+    pub(crate) fn evolve(&self, voltage: f32, target: f32, time_ns: u64, bits: u8) -> f32 {
+        let mut voltage = f64::from(voltage);
+        let target = f64::from(target);
+        let mut time_ns = time_ns as f64;
+        let slew_rate = f64::from(self.slew_rate) / 1000.0;
+        // time constant so that the error decays to 1/2 LSB (2^-(bits + 1)
+        // of the full scale) within the settling time
+        let tau =
+            f64::from(self.settling_time_ns) / (f64::from(bits + 1) * core::f64::consts::LN_2);
+        // the exponential decay is slew rate limited above this error
+        let band = slew_rate * tau;
+        let error = target - voltage;
+        let sign = if error < 0.0 { -1.0 } else { 1.0 };
+        if error.abs() > band {
+            let slewing_ns = (error.abs() - band) / slew_rate;
+            if time_ns <= slewing_ns {
+                return (voltage + sign * slew_rate * time_ns) as f32;
+            }
+            voltage = target - sign * band;
+            time_ns -= slewing_ns;
+        }
+        if tau <= 0.0 {
+            return target as f32;
+        }
+        (target - (target - voltage) * exp_neg(time_ns / tau)) as f32
+    }
+}
+
+// e^-x for x >= 0
+fn exp_neg(x: f64) -> f64 {
+    let halvings = (x / core::f64::consts::LN_2) as u64;
+    if halvings > 1000 {
+        return 0.0;
+    }
+    // e^-r for r in [0, ln 2) with the Taylor series
+    let r = x - halvings as f64 * core::f64::consts::LN_2;
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    for n in 1..16 {
+        term *= -r / f64::from(n);
+        sum += term;
+    }
+    sum * f64::from_bits((1023 - halvings) << 52)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exp_neg_is_accurate() {
+        let values = [
+            (0.0, 1.0),
+            (0.5, 0.606_530_659_712_633_4),
+            (1.0, 0.367_879_441_171_442_3),
+            (10.0, 4.539_992_976_248_485e-5),
+            (100.0, 3.720_075_976_020_836e-44),
+        ];
+        for (x, expected) in values.iter() {
+            let actual = exp_neg(*x);
+            assert!((actual - expected).abs() <= expected * 1e-12, "{}", x);
+        }
+        assert_eq!(exp_neg(1e6), 0.0);
+    }
+
+    #[test]
+    fn code_error_follows_inl_and_dnl() {
+        let params = AnalogParameters::typical(12);
+        assert_eq!(params.code_error(0, 12), -0.1);
+        assert_eq!(params.code_error(4095, 12), 0.1);
+        assert!((params.code_error(2048, 12) - 1.9).abs() < 1e-3);
+    }
+
+    #[test]
+    fn output_is_slew_rate_limited() {
+        let params = AnalogParameters::typical(12);
+        assert_eq!(params.evolve(0.0, 2.0, 1000, 12), 0.55);
+        assert_eq!(params.evolve(2.0, 0.0, 1000, 12), 1.45);
+    }
+
+    #[test]
+    fn output_settles_within_half_lsb() {
+        let params = AnalogParameters::typical(12);
+        let band = 0.55 / 1000.0 * 4500.0 / (13.0 * core::f64::consts::LN_2);
+        let slewing_ns = ((2.0 - band) / 0.55 * 1000.0) as u64;
+        let voltage = params.evolve(0.0, 2.0, slewing_ns + 4500, 12);
+        assert!((2.0 - voltage) <= 2.048 / 8192.0);
+        let voltage = params.evolve(0.0, 2.0, slewing_ns + 2000, 12);
+        assert!((2.0 - voltage) > 2.048 / 8192.0);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{
+    marker, private, BufferingSupport, Ch0, Ch1, Channel, ChannelOf, Command, Error, Mcp49xx,
+    ResolutionSupport,
+};
+use core::cell::RefCell;
+use core::convert::Infallible;
+use core::marker::PhantomData;
+use embedded_hal::spi::SpiDevice;
+
+type DualMcp49xx<SPI, RES, BUF, LDAC, SHDN> =
+    Mcp49xx<SPI, RES, marker::DualChannel, BUF, LDAC, SHDN>;
+
+// the driver is only missing before splitting it and after joining it
+type Stored<SPI, RES, BUF, LDAC, SHDN> = RefCell<Option<DualMcp49xx<SPI, RES, BUF, LDAC, SHDN>>>;
+
+/// Storage of a dual channel driver shared by the handles of its channels
+///
+/// Implemented by [`SharedMcp49xx`] and by `SyncSharedMcp49xx` when enabling
+/// the `critical-section` feature.
+pub trait SharedDriver: private::Sealed {
+    /// Shared driver
+    type Driver;
+
+    #[doc(hidden)]
+    fn with<R>(&self, f: impl FnOnce(&mut Option<Self::Driver>) -> R) -> R;
+}
+
+/// Storage of a dual channel driver shared by the handles of its channels
+/// within a single execution context
+///
+/// The handles are not `Send`, so they cannot be moved to other threads or
+/// interrupt handlers. Use `SyncSharedMcp49xx` for that.
+///
+/// See [`Mcp49xx::split()`].
+#[derive(Debug)]
+pub struct SharedMcp49xx<SPI, RES, BUF, LDAC, SHDN> {
+    dev: Stored<SPI, RES, BUF, LDAC, SHDN>,
+}
+
+impl<SPI, RES, BUF, LDAC, SHDN> SharedMcp49xx<SPI, RES, BUF, LDAC, SHDN> {
+    /// Create an empty storage.
+    pub const fn new() -> Self {
+        SharedMcp49xx {
+            dev: RefCell::new(None),
+        }
+    }
+}
+
+impl<SPI, RES, BUF, LDAC, SHDN> Default for SharedMcp49xx<SPI, RES, BUF, LDAC, SHDN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<SPI, RES, BUF, LDAC, SHDN> SharedDriver for SharedMcp49xx<SPI, RES, BUF, LDAC, SHDN> {
+    type Driver = DualMcp49xx<SPI, RES, BUF, LDAC, SHDN>;
+
+    fn with<R>(&self, f: impl FnOnce(&mut Option<Self::Driver>) -> R) -> R {
+        f(&mut self.dev.borrow_mut())
+    }
+}
+
+/// Storage of a dual channel driver shared by the handles of its channels
+/// across execution contexts
+///
+/// The driver is protected by a `critical_section::Mutex`, so the handles
+/// are `Send` (if the SPI device and pins are) and can be used from
+/// different tasks, threads or interrupt priorities.
+///
+/// Each command is sent inside a critical section, so interrupts are
+/// disabled during the whole SPI transaction, which adds to the interrupt
+/// latency. The SPI device must not wait for an interrupt or for a lock
+/// which can be held outside of a critical section (e.g. a bus shared with
+/// a `Mutex` from another thread), otherwise it deadlocks. Prefer
+/// [`SharedMcp49xx`] if both handles are used in the same context.
+///
+/// An implementation of `critical-section` must be provided by the
+/// application (usually by the HAL or the `std` feature of `critical-section`).
+/// Only available when enabling the `critical-section` feature.
+///
+/// The handles borrow the storage, so in order to move them into threads or
+/// tasks which must be `'static`, the storage must be `'static` as well:
+///
+/// ```no_run
+/// use linux_embedded_hal::SpidevDevice;
+/// use mcp49xx::{Command, Mcp49xx, SyncSharedMcp49xx};
+///
+/// let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+/// // or with the `static_cell` crate in embedded applications
+/// let shared = Box::leak(Box::new(SyncSharedMcp49xx::new()));
+/// let (mut ch0, mut ch1) = Mcp49xx::new_mcp4822(spi).split(shared);
+///
+/// let thread = std::thread::spawn(move || ch1.send(Command::default().value(3072)).unwrap());
+/// ch0.send(Command::default().value(1024)).unwrap();
+/// thread.join().unwrap();
+/// ```
+#[cfg(feature = "critical-section")]
+pub struct SyncSharedMcp49xx<SPI, RES, BUF, LDAC, SHDN> {
+    dev: critical_section::Mutex<Stored<SPI, RES, BUF, LDAC, SHDN>>,
+}
+
+#[cfg(feature = "critical-section")]
+impl<SPI, RES, BUF, LDAC, SHDN> SyncSharedMcp49xx<SPI, RES, BUF, LDAC, SHDN> {
+    /// Create an empty storage.
+    pub const fn new() -> Self {
+        SyncSharedMcp49xx {
+            dev: critical_section::Mutex::new(RefCell::new(None)),
+        }
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<SPI, RES, BUF, LDAC, SHDN> Default for SyncSharedMcp49xx<SPI, RES, BUF, LDAC, SHDN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<SPI, RES, BUF, LDAC, SHDN> SharedDriver for SyncSharedMcp49xx<SPI, RES, BUF, LDAC, SHDN> {
+    type Driver = DualMcp49xx<SPI, RES, BUF, LDAC, SHDN>;
+
+    fn with<R>(&self, f: impl FnOnce(&mut Option<Self::Driver>) -> R) -> R {
+        critical_section::with(|cs| f(&mut self.dev.borrow_ref_mut(cs)))
+    }
+}
+
+impl<SPI, RES, BUF, LDAC, SHDN> Mcp49xx<SPI, RES, marker::DualChannel, BUF, LDAC, SHDN> {
+    /// Split the driver into a handle for each channel.
+    ///
+    /// The driver is moved into the storage, which is borrowed by the
+    /// handles, so that each channel can be used independently.
+    /// Get the driver back with `join()`.
+    pub fn split<S>(self, shared: &mut S) -> (ChannelHandle<'_, Ch0, S>, ChannelHandle<'_, Ch1, S>)
+    where
+        S: SharedDriver<Driver = Self>,
+    {
+        shared.with(|dev| *dev = Some(self));
+        let shared = &*shared;
+        (
+            ChannelHandle {
+                shared,
+                _channel: PhantomData,
+            },
+            ChannelHandle {
+                shared,
+                _channel: PhantomData,
+            },
+        )
+    }
+
+    /// Get the driver back from the handles of its channels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the handles were not created by the same `split()`.
+    pub fn join<S>(ch0: ChannelHandle<'_, Ch0, S>, ch1: ChannelHandle<'_, Ch1, S>) -> Self
+    where
+        S: SharedDriver<Driver = Self>,
+    {
+        assert!(
+            core::ptr::eq(ch0.shared, ch1.shared),
+            "The handles must be created by the same split()"
+        );
+        ch0.shared.with(Option::take).unwrap()
+    }
+}
+
+/// Handle to a channel of a dual channel device
+///
+/// `C` is the channel ([`Ch0`] or [`Ch1`]) and `S` the storage shared with
+/// the handle of the other channel. Created with [`Mcp49xx::split()`].
+#[derive(Debug)]
+pub struct ChannelHandle<'a, C, S> {
+    shared: &'a S,
+    _channel: PhantomData<C>,
+}
+
+impl<C, S> ChannelHandle<'_, C, S>
+where
+    C: ChannelOf<marker::DualChannel>,
+    S: SharedDriver,
+{
+    /// Get the channel of the handle
+    pub fn channel(&self) -> Channel {
+        C::CHANNEL
+    }
+
+    fn with_dev<R>(&self, f: impl FnOnce(&mut S::Driver) -> R) -> R {
+        // the driver stays in the storage until the handles are joined
+        self.shared.with(|dev| f(dev.as_mut().unwrap()))
+    }
+}
+
+impl<C, S, SPI, RES, BUF, LDAC, SHDN> ChannelHandle<'_, C, S>
+where
+    C: ChannelOf<marker::DualChannel>,
+    S: SharedDriver<Driver = DualMcp49xx<SPI, RES, BUF, LDAC, SHDN>>,
+{
+    /// Get the last command sent to the channel.
+    ///
+    /// See `Mcp49xx::current()`.
+    pub fn current(&self) -> Option<Command> {
+        self.with_dev(|dev| dev.current(C::CHANNEL))
+    }
+}
+
+impl<C, S, SPI, RES, BUF, LDAC, SHDN, CommE> ChannelHandle<'_, C, S>
+where
+    C: ChannelOf<marker::DualChannel>,
+    S: SharedDriver<Driver = DualMcp49xx<SPI, RES, BUF, LDAC, SHDN>>,
+    SPI: SpiDevice<u8, Error = CommE>,
+    RES: ResolutionSupport<CommE, Infallible>,
+    BUF: BufferingSupport<CommE, Infallible>,
+{
+    /// Send command to the channel of the handle.
+    ///
+    /// The channel selected in the command is ignored.
+    /// Otherwise, this behaves like `Mcp49xx::send()`.
+    pub fn send(&mut self, command: Command) -> Result<(), Error<CommE>> {
+        self.with_dev(|dev| dev.send(command.channel(C::CHANNEL)))
+    }
+}
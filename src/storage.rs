@@ -0,0 +1,434 @@
+use crate::{Calibration, Mcp49xx, MAX_CALIBRATION_POINTS};
+
+/// Identifier at the beginning of the serialized calibration data
+const MAGIC: [u8; 2] = *b"MC";
+/// Version of the serialized calibration data format
+const VERSION: u8 = 1;
+/// Header size: magic, version and flags
+const HEADER_SIZE: usize = 4;
+/// Size of a serialized calibration with no table points
+const CALIBRATION_SIZE: usize = 9;
+/// Size of a serialized table point
+const POINT_SIZE: usize = 4;
+/// Size of the checksum
+const CHECKSUM_SIZE: usize = 2;
+
+/// Maximum size of the serialized calibration data in bytes
+pub const CALIBRATION_DATA_MAX_SIZE: usize = HEADER_SIZE
+    + 2 * (CALIBRATION_SIZE + MAX_CALIBRATION_POINTS * POINT_SIZE)
+    + 2 * 4
+    + CHECKSUM_SIZE;
+
+/// Errors serializing or deserializing calibration data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CalibrationDataError {
+    /// The buffer provided is too small
+    BufferTooSmall,
+    /// The data does not start with the expected identifier
+    InvalidHeader,
+    /// The data was serialized with an unsupported format version
+    UnsupportedVersion(u8),
+    /// The checksum does not match the data
+    InvalidChecksum,
+    /// The data contains invalid values
+    InvalidData,
+}
+
+/// Calibration and reference voltage settings of both channels
+///
+/// These can be serialized into a compact binary format with a format
+/// version and a CRC-16 checksum in order to store them in non-volatile memory.
+///
+/// The format is: the `"MC"` identifier, the version, a byte flagging the
+/// items present, each calibration present (offset and gain as
+/// little-endian `f32`, number of table points and the points as pairs of
+/// little-endian `u16`), each reference voltage present (little-endian
+/// `f32`) and the CRC-16/CCITT-FALSE of all the previous bytes (big-endian).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CalibrationData {
+    /// Calibration of each channel
+    pub calibration: [Option<Calibration>; 2],
+    /// External reference voltage of each channel (MCP49xx)
+    pub vref: [Option<f32>; 2],
+}
+
+impl CalibrationData {
+    /// Serialize the data into a buffer.
+    ///
+    /// Returns the number of bytes written, which is at most
+    /// `CALIBRATION_DATA_MAX_SIZE`.
+    pub fn serialize(&self, buffer: &mut [u8]) -> Result<usize, CalibrationDataError> {
+        let mut writer = Writer { buffer, len: 0 };
+        let mut flags = 0;
+        for (i, cal) in self.calibration.iter().enumerate() {
+            if cal.is_some() {
+                flags |= 1 << i;
+            }
+        }
+        for (i, vref) in self.vref.iter().enumerate() {
+            if vref.is_some() {
+                flags |= 1 << (i + 2);
+            }
+        }
+        writer.write(&MAGIC)?;
+        writer.write(&[VERSION, flags])?;
+        for cal in self.calibration.iter().flatten() {
+            writer.write(&cal.offset().to_le_bytes())?;
+            writer.write(&cal.gain().to_le_bytes())?;
+            writer.write(&[cal.points().len() as u8])?;
+            for (code, corrected) in cal.points() {
+                writer.write(&code.to_le_bytes())?;
+                writer.write(&corrected.to_le_bytes())?;
+            }
+        }
+        for vref in self.vref.iter().flatten() {
+            writer.write(&vref.to_le_bytes())?;
+        }
+        let checksum = crc16(&writer.buffer[..writer.len]);
+        writer.write(&checksum.to_be_bytes())?;
+        Ok(writer.len)
+    }
+
+    /// Deserialize the data from a buffer.
+    ///
+    /// Any bytes after the checksum are ignored.
+    ///
+    /// The identifier and the version are checked first since the length of
+    /// the data depends on the format. Then the checksum is validated before
+    /// the values, so that corrupted flags, point counts or values are reported
+    /// as `InvalidChecksum`, unless they make the data longer than the buffer.
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, CalibrationDataError> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.read::<2>()? != MAGIC {
+            return Err(CalibrationDataError::InvalidHeader);
+        }
+        let [version, flags] = reader.read::<2>()?;
+        if version != VERSION {
+            return Err(CalibrationDataError::UnsupportedVersion(version));
+        }
+        // validate the checksum before the values so that corrupted data is
+        // reported as such
+        let len = Self::data_len(bytes, flags)?;
+        let checksum = bytes
+            .get(len..len + CHECKSUM_SIZE)
+            .ok_or(CalibrationDataError::BufferTooSmall)?;
+        if u16::from_be_bytes([checksum[0], checksum[1]]) != crc16(&bytes[..len]) {
+            return Err(CalibrationDataError::InvalidChecksum);
+        }
+        if flags & 0b1111_0000 != 0 {
+            return Err(CalibrationDataError::InvalidData);
+        }
+        let mut data = CalibrationData::default();
+        for i in 0..2 {
+            if flags & (1 << i) != 0 {
+                data.calibration[i] = Some(reader.read_calibration()?);
+            }
+        }
+        for i in 0..2 {
+            if flags & (1 << (i + 2)) != 0 {
+                data.vref[i] = Some(f32::from_le_bytes(reader.read()?));
+            }
+        }
+        Ok(data)
+    }
+
+    // length of the data before the checksum according to the flags and
+    // the number of table points
+    fn data_len(bytes: &[u8], flags: u8) -> Result<usize, CalibrationDataError> {
+        let mut len = HEADER_SIZE;
+        for i in 0..2 {
+            if flags & (1 << i) != 0 {
+                let count = bytes
+                    .get(len + CALIBRATION_SIZE - 1)
+                    .ok_or(CalibrationDataError::BufferTooSmall)?;
+                len += CALIBRATION_SIZE + usize::from(*count) * POINT_SIZE;
+            }
+        }
+        Ok(len + 4 * ((flags >> 2) & 0b11).count_ones() as usize)
+    }
+}
+
+struct Writer<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl Writer<'_> {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), CalibrationDataError> {
+        let end = self.len + bytes.len();
+        self.buffer
+            .get_mut(self.len..end)
+            .ok_or(CalibrationDataError::BufferTooSmall)?
+            .copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn read<const N: usize>(&mut self) -> Result<[u8; N], CalibrationDataError> {
+        let mut value = [0; N];
+        value.copy_from_slice(
+            self.bytes
+                .get(self.pos..self.pos + N)
+                .ok_or(CalibrationDataError::BufferTooSmall)?,
+        );
+        self.pos += N;
+        Ok(value)
+    }
+
+    fn read_calibration(&mut self) -> Result<Calibration, CalibrationDataError> {
+        let offset = f32::from_le_bytes(self.read()?);
+        let gain = f32::from_le_bytes(self.read()?);
+        let [count] = self.read::<1>()?;
+        let count = usize::from(count);
+        if count > MAX_CALIBRATION_POINTS {
+            return Err(CalibrationDataError::InvalidData);
+        }
+        let mut points = [(0, 0); MAX_CALIBRATION_POINTS];
+        for point in points.iter_mut().take(count) {
+            *point = (
+                u16::from_le_bytes(self.read()?),
+                u16::from_le_bytes(self.read()?),
+            );
+        }
+        Calibration::new(offset, gain)
+            .with_points(&points[..count])
+            .ok_or(CalibrationDataError::InvalidData)
+    }
+}
+
+// CRC-16/CCITT-FALSE
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for byte in bytes {
+        crc ^= u16::from(*byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN> {
+    /// Get the calibration and external reference voltage of both channels.
+    pub fn calibration_data(&self) -> CalibrationData {
+        CalibrationData {
+            calibration: self.state.calibration,
+            vref: self.state.vref,
+        }
+    }
+
+    /// Set the calibration and external reference voltage of both channels.
+    ///
+    /// The reference voltages are ignored in MCP48xx devices, which use an
+    /// internal reference.
+    pub fn set_calibration_data(&mut self, data: CalibrationData) {
+        self.state.calibration = data.calibration;
+        self.state.vref = data.vref;
+    }
+}
+
+#[cfg(feature = "storage")]
+mod flash {
+    use super::{CalibrationData, CalibrationDataError, CALIBRATION_DATA_MAX_SIZE};
+    use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+
+    /// Size of the buffer used for flash access, which limits the supported
+    /// read and write sizes
+    const BUFFER_SIZE: usize = 128;
+
+    /// Errors storing or loading calibration data
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum StorageError<E> {
+        /// Flash error
+        Flash(E),
+        /// Invalid calibration data
+        Data(CalibrationDataError),
+    }
+
+    impl CalibrationData {
+        /// Store the data in NOR flash at the given offset.
+        ///
+        /// The offset must be aligned to the flash erase size. The erase
+        /// sectors needed to hold `CALIBRATION_DATA_MAX_SIZE` bytes are
+        /// erased before writing.
+        /// Only available when enabling the `storage` feature.
+        pub fn save<F: NorFlash>(
+            &self,
+            flash: &mut F,
+            offset: u32,
+        ) -> Result<(), StorageError<F::Error>> {
+            let mut buffer = [0xFF; BUFFER_SIZE];
+            let len = self.serialize(&mut buffer).map_err(StorageError::Data)?;
+            let len = round_up(len, F::WRITE_SIZE);
+            if len > BUFFER_SIZE {
+                return Err(StorageError::Data(CalibrationDataError::BufferTooSmall));
+            }
+            let erase_len = round_up(CALIBRATION_DATA_MAX_SIZE, F::ERASE_SIZE);
+            flash
+                .erase(offset, offset + erase_len as u32)
+                .map_err(StorageError::Flash)?;
+            flash
+                .write(offset, &buffer[..len])
+                .map_err(StorageError::Flash)
+        }
+
+        /// Load the data from NOR flash at the given offset.
+        ///
+        /// Only available when enabling the `storage` feature.
+        pub fn load<F: ReadNorFlash>(
+            flash: &mut F,
+            offset: u32,
+        ) -> Result<Self, StorageError<F::Error>> {
+            let mut buffer = [0; BUFFER_SIZE];
+            let len = round_up(CALIBRATION_DATA_MAX_SIZE, F::READ_SIZE);
+            if len > BUFFER_SIZE {
+                return Err(StorageError::Data(CalibrationDataError::BufferTooSmall));
+            }
+            let len = len.min(flash.capacity().saturating_sub(offset as usize));
+            flash
+                .read(offset, &mut buffer[..len])
+                .map_err(StorageError::Flash)?;
+            CalibrationData::deserialize(&buffer[..len]).map_err(StorageError::Data)
+        }
+    }
+
+    fn round_up(len: usize, size: usize) -> usize {
+        (len + size - 1) / size * size
+    }
+}
+#[cfg(feature = "storage")]
+pub use flash::StorageError;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> CalibrationData {
+        let cal = Calibration::new(-1.5, 1.002)
+            .with_points(&[(0, 1), (2048, 2050), (4095, 4093)])
+            .unwrap();
+        CalibrationData {
+            calibration: [Some(cal), None],
+            vref: [Some(2.5), Some(4.096)],
+        }
+    }
+
+    #[test]
+    fn crc_matches_reference() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+    }
+
+    #[test]
+    fn can_round_trip() {
+        let data = example();
+        let mut buffer = [0; CALIBRATION_DATA_MAX_SIZE];
+        let len = data.serialize(&mut buffer).unwrap();
+        assert_eq!(len, HEADER_SIZE + CALIBRATION_SIZE + 3 * POINT_SIZE + 8 + 2);
+        assert_eq!(CalibrationData::deserialize(&buffer[..len]), Ok(data));
+    }
+
+    #[test]
+    fn can_round_trip_empty() {
+        let mut buffer = [0; CALIBRATION_DATA_MAX_SIZE];
+        let len = CalibrationData::default().serialize(&mut buffer).unwrap();
+        assert_eq!(&buffer[..4], &[b'M', b'C', VERSION, 0]);
+        assert_eq!(len, HEADER_SIZE + CHECKSUM_SIZE);
+        assert_eq!(
+            CalibrationData::deserialize(&buffer[..len]),
+            Ok(CalibrationData::default())
+        );
+    }
+
+    #[test]
+    fn max_size_fits_full_data() {
+        let mut points = [(0, 0); MAX_CALIBRATION_POINTS];
+        for (i, point) in points.iter_mut().enumerate() {
+            *point = (i as u16, i as u16);
+        }
+        let cal = Calibration::default().with_points(&points).unwrap();
+        let data = CalibrationData {
+            calibration: [Some(cal); 2],
+            vref: [Some(1.0); 2],
+        };
+        let mut buffer = [0; CALIBRATION_DATA_MAX_SIZE];
+        assert_eq!(data.serialize(&mut buffer), Ok(CALIBRATION_DATA_MAX_SIZE));
+    }
+
+    #[test]
+    fn cannot_serialize_into_small_buffer() {
+        let mut buffer = [0; 10];
+        assert_eq!(
+            example().serialize(&mut buffer),
+            Err(CalibrationDataError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn detects_corrupted_data() {
+        let mut buffer = [0; CALIBRATION_DATA_MAX_SIZE];
+        let len = example().serialize(&mut buffer).unwrap();
+        buffer[6] ^= 0x01;
+        assert_eq!(
+            CalibrationData::deserialize(&buffer[..len]),
+            Err(CalibrationDataError::InvalidChecksum)
+        );
+    }
+
+    #[test]
+    fn detects_corrupted_structure() {
+        let mut buffer = [0; CALIBRATION_DATA_MAX_SIZE];
+        example().serialize(&mut buffer).unwrap();
+        // flags and number of table points
+        for (index, mask) in [(3, 0x80), (3, 0x08), (12, 0x04), (12, 0x08)] {
+            let mut corrupted = buffer;
+            corrupted[index] ^= mask;
+            assert_eq!(
+                CalibrationData::deserialize(&corrupted),
+                Err(CalibrationDataError::InvalidChecksum)
+            );
+        }
+    }
+
+    #[test]
+    fn detects_invalid_header() {
+        let mut buffer = [0; CALIBRATION_DATA_MAX_SIZE];
+        let len = example().serialize(&mut buffer).unwrap();
+        buffer[0] = 0xFF;
+        assert_eq!(
+            CalibrationData::deserialize(&buffer[..len]),
+            Err(CalibrationDataError::InvalidHeader)
+        );
+    }
+
+    #[test]
+    fn detects_unsupported_version() {
+        let mut buffer = [0; CALIBRATION_DATA_MAX_SIZE];
+        let len = example().serialize(&mut buffer).unwrap();
+        buffer[2] = 2;
+        assert_eq!(
+            CalibrationData::deserialize(&buffer[..len]),
+            Err(CalibrationDataError::UnsupportedVersion(2))
+        );
+    }
+
+    #[test]
+    fn detects_truncated_data() {
+        let mut buffer = [0; CALIBRATION_DATA_MAX_SIZE];
+        let len = example().serialize(&mut buffer).unwrap();
+        assert_eq!(
+            CalibrationData::deserialize(&buffer[..len - 1]),
+            Err(CalibrationDataError::BufferTooSmall)
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{
+    marker, BufferingSupport, Channel, ChannelSupport, Command, Error, Mcp49xx, ReferenceSupport,
+    ResolutionSupport,
+};
+use core::convert::Infallible;
+use embedded_hal::spi::SpiDevice;
+
+impl<SPI, RES, CH, LDAC, SHDN> Mcp49xx<SPI, RES, CH, marker::Buffered, LDAC, SHDN> {
+    /// Set the voltage applied to the external reference input of all channels (MCP49xx).
+    ///
+    /// This is used to convert voltages into values in `set_voltage()` and
+    /// `send_voltage()`. The MCP48xx devices use an internal reference.
+    pub fn set_vref(&mut self, vref: f32) {
+        self.state.vref = [Some(vref); 2];
+    }
+}
+
+impl<SPI, RES, LDAC, SHDN> Mcp49xx<SPI, RES, marker::DualChannel, marker::Buffered, LDAC, SHDN> {
+    /// Set the voltage applied to the external reference input of a channel (MCP49x2).
+    ///
+    /// The reference of channel 0 is VREFA and the reference of channel 1 is VREFB.
+    pub fn set_channel_vref(&mut self, channel: Channel, vref: f32) {
+        self.state.vref[channel.index()] = Some(vref);
+    }
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN> {
+    /// Set the supply voltage and the headroom the output needs below it.
+    ///
+    /// Voltages above `vdd - headroom` are rejected with
+    /// `Error::VddHeadroomExceeded` when using double gain (2x), where the
+    /// full-scale range can exceed the supply voltage.
+    /// No check is done if this has not been set.
+    pub fn set_vdd(&mut self, vdd: f32, headroom: f32) {
+        self.state.max_output = Some(vdd - headroom);
+    }
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN>
+where
+    BUF: ReferenceSupport,
+{
+    /// Get the reference voltage of a channel.
+    ///
+    /// This is the internal 2.048V reference for the MCP48xx devices and
+    /// the voltage configured with `set_vref()` or `set_channel_vref()`
+    /// for the MCP49xx devices.
+    pub fn vref(&self, channel: Channel) -> Option<f32> {
+        BUF::INTERNAL_VREF.or(self.state.vref[channel.index()])
+    }
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN, CommE> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN>
+where
+    SPI: SpiDevice<u8, Error = CommE>,
+    RES: ResolutionSupport<CommE, Infallible>,
+    CH: ChannelSupport<CommE, Infallible>,
+    BUF: BufferingSupport<CommE, Infallible> + ReferenceSupport,
+{
+    /// Set a channel output to a voltage.
+    ///
+    /// The gain and buffering of the last command sent to the channel are
+    /// kept and the channel is enabled. See `send_voltage()`.
+    pub fn set_voltage(&mut self, channel: Channel, voltage: f32) -> Result<f32, Error<CommE>> {
+        let command = self.current(channel).unwrap_or_default();
+        self.send_voltage(command.channel(channel).enable(), voltage)
+    }
+
+    /// Set a channel output to a voltage selecting the gain automatically.
+    ///
+    /// Single gain (1x) is selected if the voltage is within its full-scale
+    /// range, since it provides the best resolution. Otherwise double gain
+    /// (2x) is selected.
+    /// The buffering of the last command sent to the channel is kept and
+    /// the channel is enabled. See `send_voltage()`.
+    pub fn set_voltage_auto_gain(
+        &mut self,
+        channel: Channel,
+        voltage: f32,
+    ) -> Result<f32, Error<CommE>> {
+        let vref = self.vref(channel).ok_or(Error::NoReference)?;
+        let command = self.current(channel).unwrap_or_default();
+        let command = command.channel(channel).enable();
+        let command = if voltage_to_code(voltage, vref, RES::BITS).is_some() {
+            command.single_gain()
+        } else {
+            command.double_gain()
+        };
+        self.send_voltage(command, voltage)
+    }
+
+    /// Send a command setting the value to the closest code to a voltage.
+    ///
+    /// The full-scale range is given by the reference voltage of the channel
+    /// and the gain selected in the command. The value in the command is ignored.
+    /// Returns the output voltage actually achieved.
+    ///
+    /// This will return `Error::NoReference` if the reference voltage of the
+    /// channel has not been set in a MCP49xx device and `Error::InvalidValue` if the
+    /// voltage is negative or outside the full-scale range.
+    /// With double gain, `Error::VddHeadroomExceeded` will be returned if the
+    /// output voltage would be too close to the supply set with `set_vdd()`.
+    /// Otherwise, the same errors as in `send()` can be returned.
+    pub fn send_voltage(&mut self, command: Command, voltage: f32) -> Result<f32, Error<CommE>> {
+        let vref = self.vref(command.channel).ok_or(Error::NoReference)?;
+        let full_scale = if command.is_double_gain() {
+            2.0 * vref
+        } else {
+            vref
+        };
+        let code = voltage_to_code(voltage, full_scale, RES::BITS).ok_or(Error::InvalidValue)?;
+        let achieved = code_to_voltage(code, full_scale, RES::BITS);
+        if let (true, Some(max_output)) = (command.is_double_gain(), self.state.max_output) {
+            if achieved > max_output {
+                return Err(Error::VddHeadroomExceeded);
+            }
+        }
+        self.send_code(command.value(code))?;
+        Ok(achieved)
+    }
+}
+
+// Round a voltage to the closest code. Returns `None` if it is out of range.
+pub(crate) fn voltage_to_code(voltage: f32, full_scale: f32, bits: u8) -> Option<u16> {
+    let steps = (1_u32 << bits) as f32;
+    let code = voltage * steps / full_scale;
+    // negated comparison so that NaN is rejected as well
+    if !(code >= 0.0 && code < steps - 0.5) {
+        return None;
+    }
+    Some((code + 0.5) as u16)
+}
+
+pub(crate) fn code_to_voltage(code: u16, full_scale: f32, bits: u8) -> f32 {
+    f32::from(code) * full_scale / (1_u32 << bits) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_to_closest_code() {
+        assert_eq!(voltage_to_code(1.0, 4.096, 12), Some(1000));
+        assert_eq!(voltage_to_code(1.0004, 4.096, 12), Some(1000));
+        assert_eq!(voltage_to_code(1.0006, 4.096, 12), Some(1001));
+        assert_eq!(voltage_to_code(0.0, 4.096, 12), Some(0));
+    }
+
+    #[test]
+    fn rejects_out_of_range_voltage() {
+        assert_eq!(voltage_to_code(-0.1, 2.048, 8), None);
+        assert_eq!(voltage_to_code(2.048, 2.048, 8), None);
+        assert_eq!(voltage_to_code(f32::NAN, 2.048, 8), None);
+        assert_eq!(voltage_to_code(1.0, 0.0, 8), None);
+    }
+
+    #[test]
+    fn accepts_top_of_range() {
+        assert_eq!(voltage_to_code(2.04, 2.048, 8), Some(255));
+    }
+
+    #[test]
+    fn converts_code_to_voltage() {
+        assert_eq!(code_to_voltage(512, 2.048, 10), 1.024);
+        assert_eq!(code_to_voltage(0, 2.048, 10), 0.0);
+    }
+}
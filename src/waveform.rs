@@ -0,0 +1,545 @@
+//! Waveform generation
+//!
+//! A [`Waveform`] produces the values of a periodic signal for the resolution
+//! of a device. The values can be streamed to a channel with
+//! [`send_samples()`](crate::Mcp49xx::send_samples).
+//!
+//! Full-scale tables and the SPI frames to send them can also be created at
+//! compile time with [`sine_table()`] and the like, and [`frames()`].
+//!
+//! ```
+//! use mcp49xx::{marker::Resolution12Bit, waveform::Waveform};
+//!
+//! // Square signal between 25% and 75% of the full scale, high 20% of the period
+//! let square = Waveform::square(0.2).amplitude(0.25).offset(0.5);
+//! let values: [u16; 4] = [0, 1, 2, 3].map(|i| square.sample::<Resolution12Bit>(i << 30));
+//! assert_eq!(values, [3071, 1024, 1024, 1024]);
+//! ```
+
+use crate::{
+    BufferingSupport, ChannelSupport, Command, Error, Mcp49xx, Resolution, ResolutionSupport,
+};
+use core::convert::Infallible;
+use core::marker::PhantomData;
+use embedded_hal::{delay::DelayNs, spi::SpiDevice};
+
+/// Full-scale level of the normalized signals (Q30 fixed point)
+pub(crate) const LEVEL_ONE: i32 = 1 << 30;
+
+/// Shape of a waveform
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    /// Sine, starting at the offset and rising.
+    Sine,
+    /// Triangle, starting at the offset and rising.
+    Triangle,
+    /// Sawtooth, rising from the minimum to the maximum.
+    Sawtooth,
+    /// Square, at the maximum during the first part of the period.
+    Square {
+        /// Fraction of the period where the signal is at the maximum [0.0, 1.0]
+        duty: f32,
+    },
+}
+
+/// Periodic signal
+///
+/// The amplitude and offset are fractions of the full scale of the device.
+/// By default the signal spans the whole range (amplitude 0.5, offset 0.5).
+/// Values out of range are clipped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Waveform {
+    shape: Shape,
+    amplitude: f32,
+    offset: f32,
+}
+
+impl Waveform {
+    /// Create a new waveform spanning the whole range.
+    pub fn new(shape: Shape) -> Self {
+        Waveform {
+            shape,
+            amplitude: 0.5,
+            offset: 0.5,
+        }
+    }
+
+    /// Create a new sine waveform.
+    pub fn sine() -> Self {
+        Self::new(Shape::Sine)
+    }
+
+    /// Create a new triangle waveform.
+    pub fn triangle() -> Self {
+        Self::new(Shape::Triangle)
+    }
+
+    /// Create a new sawtooth waveform.
+    pub fn sawtooth() -> Self {
+        Self::new(Shape::Sawtooth)
+    }
+
+    /// Create a new square waveform with a duty cycle [0.0, 1.0].
+    pub fn square(duty: f32) -> Self {
+        Self::new(Shape::Square { duty })
+    }
+
+    /// Set the amplitude (peak) as a fraction of the full scale.
+    pub fn amplitude(mut self, amplitude: f32) -> Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+    /// Set the offset (center) as a fraction of the full scale.
+    pub fn offset(mut self, offset: f32) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Get the shape
+    pub fn get_shape(&self) -> Shape {
+        self.shape
+    }
+
+    /// Get the amplitude
+    pub fn get_amplitude(&self) -> f32 {
+        self.amplitude
+    }
+
+    /// Get the offset
+    pub fn get_offset(&self) -> f32 {
+        self.offset
+    }
+
+    /// Get the value at a phase for a resolution.
+    ///
+    /// The phase spans a whole period over the `u32` range.
+    pub fn sample<RES: Resolution>(&self, phase: u32) -> u16 {
+        let level = match self.shape {
+            Shape::Sine => sine(phase),
+            Shape::Triangle => triangle(phase),
+            Shape::Sawtooth => sawtooth(phase),
+            Shape::Square { duty } => square(phase, duty_to_phase(duty)),
+        };
+        let level = level as f32 / LEVEL_ONE as f32;
+        let max = ((1u32 << RES::BITS) - 1) as f32;
+        let code = (self.offset + self.amplitude * level) * max;
+        // also maps NaN to 0
+        (code.clamp(0.0, max) + 0.5) as u16
+    }
+
+    /// Fill a table with one period of the waveform.
+    ///
+    /// This is useful to create the table of a [`Dds`](crate::dds::Dds).
+    pub fn fill<RES: Resolution>(&self, table: &mut [u16]) {
+        let samples = self.samples::<RES>(table.len() as u32);
+        for (entry, value) in table.iter_mut().zip(samples) {
+            *entry = value;
+        }
+    }
+
+    /// Get an iterator over the values of one period divided in `count` samples.
+    ///
+    /// Use `cycle()` on it to repeat the period.
+    pub fn samples<RES: Resolution>(&self, count: u32) -> Samples<RES> {
+        Samples {
+            waveform: *self,
+            count,
+            index: 0,
+            _resolution: PhantomData,
+        }
+    }
+}
+
+/// Iterator over the values of one period of a waveform
+///
+/// Created with [`Waveform::samples()`].
+#[derive(Debug, Clone)]
+pub struct Samples<RES> {
+    waveform: Waveform,
+    count: u32,
+    index: u32,
+    _resolution: PhantomData<RES>,
+}
+
+impl<RES: Resolution> Iterator for Samples<RES> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        if self.index >= self.count {
+            return None;
+        }
+        let phase = ((u64::from(self.index) << 32) / u64::from(self.count)) as u32;
+        self.index += 1;
+        Some(self.waveform.sample::<RES>(phase))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.count - self.index) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<RES: Resolution> ExactSizeIterator for Samples<RES> {}
+
+/// Taylor series coefficients of sin(x * pi / 2) for odd powers of x (Q30)
+const SINE_COEFFICIENTS: [i64; 5] = [1_686_629_713, -693_598_668, 85_569_306, -5_026_995, 172_272];
+
+/// Sine of a phase spanning a whole period over the `u32` range (Q30).
+///
+/// The error is below 4e-6 of the full scale.
+pub(crate) const fn sine(phase: u32) -> i32 {
+    let quadrant = phase >> 30;
+    let mut x = (phase & 0x3FFF_FFFF) as i64;
+    if quadrant & 1 == 1 {
+        x = LEVEL_ONE as i64 - x;
+    }
+    let x2 = (x * x) >> 30;
+    let mut acc = SINE_COEFFICIENTS[4];
+    let mut i = 4;
+    while i > 0 {
+        i -= 1;
+        acc = SINE_COEFFICIENTS[i] + ((acc * x2) >> 30);
+    }
+    let mut level = (acc * x) >> 30;
+    if level > LEVEL_ONE as i64 {
+        level = LEVEL_ONE as i64;
+    }
+    if quadrant >= 2 {
+        -level as i32
+    } else {
+        level as i32
+    }
+}
+
+/// Triangle of a phase spanning a whole period over the `u32` range (Q30).
+pub(crate) const fn triangle(phase: u32) -> i32 {
+    // Shifted by a quarter period so that it starts at 0 rising.
+    let shifted = phase.wrapping_add(1 << 30);
+    // Distance to the middle of the period, in [0, 2^31]
+    let distance = (shifted as i64 - (1 << 31)).abs();
+    (LEVEL_ONE as i64 - distance) as i32
+}
+
+/// Sawtooth of a phase spanning a whole period over the `u32` range (Q30).
+pub(crate) const fn sawtooth(phase: u32) -> i32 {
+    ((phase >> 1) as i32).wrapping_sub(LEVEL_ONE)
+}
+
+/// Square of a phase spanning a whole period over the `u32` range (Q30).
+///
+/// The signal is at the maximum while the phase is below `duty`.
+pub(crate) const fn square(phase: u32, duty: u64) -> i32 {
+    if (phase as u64) < duty {
+        LEVEL_ONE
+    } else {
+        -LEVEL_ONE
+    }
+}
+
+/// Convert a duty cycle fraction into the phase where the signal goes low.
+fn duty_to_phase(duty: f32) -> u64 {
+    // also maps NaN to 0
+    (duty.clamp(0.0, 1.0) * (1u64 << 32) as f32) as u64
+}
+
+#[derive(Clone, Copy)]
+enum TableShape {
+    Sine,
+    Triangle,
+    Sawtooth,
+}
+
+/// Get the maximum code for a resolution, panicking if it is not supported.
+const fn max_code(bits: u8) -> u16 {
+    if !(bits == 8 || bits == 10 || bits == 12) {
+        panic!("Resolution not supported");
+    }
+    ((1u32 << bits) - 1) as u16
+}
+
+const fn full_scale_table<const N: usize>(shape: TableShape, bits: u8) -> [u16; N] {
+    let max = max_code(bits) as i64;
+    let mut table = [0; N];
+    let mut i = 0;
+    while i < N {
+        let phase = (((i as u64) << 32) / N as u64) as u32;
+        let level = match shape {
+            TableShape::Sine => sine(phase),
+            TableShape::Triangle => triangle(phase),
+            TableShape::Sawtooth => sawtooth(phase),
+        };
+        // map [-1, 1] to [0, max] rounding to the closest code
+        let level = level as i64 + LEVEL_ONE as i64;
+        table[i] = ((level * max + LEVEL_ONE as i64) >> 31) as u16;
+        i += 1;
+    }
+    table
+}
+
+/// Create a table with one period of a full-scale sine in `N` samples.
+///
+/// This can be evaluated at compile time, so that the table is stored in flash:
+///
+/// ```
+/// use mcp49xx::{marker::Resolution10Bit, waveform::sine_table, Resolution};
+///
+/// const SINE: [u16; 256] = sine_table(<Resolution10Bit as Resolution>::BITS);
+/// assert_eq!(SINE[64], 1023);
+/// ```
+///
+/// Panics if the number of bits is not 8, 10 or 12.
+pub const fn sine_table<const N: usize>(bits: u8) -> [u16; N] {
+    full_scale_table(TableShape::Sine, bits)
+}
+
+/// Create a table with one period of a full-scale triangle in `N` samples.
+///
+/// See [`sine_table()`].
+pub const fn triangle_table<const N: usize>(bits: u8) -> [u16; N] {
+    full_scale_table(TableShape::Triangle, bits)
+}
+
+/// Create a table with one period of a full-scale sawtooth in `N` samples.
+///
+/// See [`sine_table()`].
+pub const fn sawtooth_table<const N: usize>(bits: u8) -> [u16; N] {
+    full_scale_table(TableShape::Sawtooth, bits)
+}
+
+/// Create a table with one period of a full-scale square in `N` samples,
+/// where the first `high` samples are at the maximum.
+///
+/// See [`sine_table()`].
+pub const fn square_table<const N: usize>(bits: u8, high: usize) -> [u16; N] {
+    let max = max_code(bits);
+    let mut table = [0; N];
+    let mut i = 0;
+    while i < N && i < high {
+        table[i] = max;
+        i += 1;
+    }
+    table
+}
+
+/// Create the SPI frames which send each of the values with the settings
+/// (channel, gain, buffering...) of the command.
+///
+/// The frames are the same that `send()` writes for a device with this
+/// resolution if no calibration is set. The validity of the command for a
+/// device is not checked.
+/// This can be evaluated at compile time, so that the frames are stored in flash:
+///
+/// ```
+/// use mcp49xx::{marker::Resolution12Bit, waveform, Channel, Command, Resolution};
+///
+/// const BITS: u8 = <Resolution12Bit as Resolution>::BITS;
+/// const FRAMES: [[u8; 2]; 64] = waveform::frames(
+///     Command::new().channel(Channel::Ch1),
+///     &waveform::triangle_table(BITS),
+///     BITS,
+/// );
+/// assert_eq!(FRAMES[16], [0b1011_1111, 0b1111_1111]);
+/// ```
+///
+/// Panics if the number of bits is not 8, 10 or 12 or if a value does not
+/// fit in them.
+pub const fn frames<const N: usize>(command: Command, values: &[u16; N], bits: u8) -> [[u8; 2]; N] {
+    let max = max_code(bits);
+    let mut frames = [[0; 2]; N];
+    let mut i = 0;
+    while i < N {
+        if values[i] > max {
+            panic!("Value out of range");
+        }
+        frames[i] = command.value(values[i]).encode_bits(bits);
+        i += 1;
+    }
+    frames
+}
+
+impl<SPI, RES, CH, BUF, LDAC, SHDN, CommE> Mcp49xx<SPI, RES, CH, BUF, LDAC, SHDN>
+where
+    SPI: SpiDevice<u8, Error = CommE>,
+    RES: ResolutionSupport<CommE, Infallible>,
+    CH: ChannelSupport<CommE, Infallible>,
+    BUF: BufferingSupport<CommE, Infallible>,
+{
+    /// Send a sequence of codes, waiting the sample period after each one.
+    ///
+    /// The rest of the settings (channel, gain, buffering...) are taken from
+    /// the command. Each code is sent as in `send()` and the first error
+    /// stops the sequence. The samples are codes for the resolution of the
+    /// device, like the ones generated by a [`Waveform`], so the value policy
+    /// does not apply to them.
+    ///
+    /// The time spent in the communication is not compensated, so the actual
+    /// sample period is `sample_period_ns` plus the time needed to send a command.
+    ///
+    /// ```no_run
+    /// use linux_embedded_hal::{Delay, SpidevDevice};
+    /// use mcp49xx::{marker::Resolution12Bit, waveform::Waveform, Command, Mcp49xx};
+    ///
+    /// let spi = SpidevDevice::open("/dev/spidev0.0").unwrap();
+    /// let mut dac = Mcp49xx::new_mcp4921(spi);
+    /// let sine = Waveform::sine().samples::<Resolution12Bit>(100);
+    /// // Send 10 periods of a 10 Hz sine
+    /// dac.send_samples(Command::default(), sine.cycle().take(1000), &mut Delay, 1_000_000)
+    ///     .unwrap();
+    /// ```
+    pub fn send_samples<I, D>(
+        &mut self,
+        command: Command,
+        samples: I,
+        delay: &mut D,
+        sample_period_ns: u32,
+    ) -> Result<(), Error<CommE>>
+    where
+        I: IntoIterator<Item = u16>,
+        D: DelayNs,
+    {
+        for value in samples {
+            self.send_code(command.value(value))?;
+            delay.delay_ns(sample_period_ns);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::marker::{Resolution10Bit, Resolution12Bit, Resolution8Bit};
+    use crate::Channel;
+
+    fn to_f64(level: i32) -> f64 {
+        f64::from(level) / f64::from(LEVEL_ONE)
+    }
+
+    #[test]
+    fn sine_is_accurate() {
+        for i in 0..=1024u64 {
+            let phase = ((i << 32) / 1024) as u32;
+            let expected = (i as f64 / 1024.0 * 2.0 * core::f64::consts::PI).sin();
+            let error = (to_f64(sine(phase)) - expected).abs();
+            assert!(error < 4e-6, "phase {}: error {}", phase, error);
+        }
+    }
+
+    #[test]
+    fn shapes_have_expected_levels() {
+        let quarter = 1 << 30;
+        assert_eq!(triangle(0), 0);
+        assert_eq!(triangle(quarter), LEVEL_ONE);
+        assert_eq!(triangle(2 * quarter), 0);
+        assert_eq!(triangle(3 * quarter), -LEVEL_ONE);
+        assert_eq!(sawtooth(0), -LEVEL_ONE);
+        assert_eq!(sawtooth(2 * quarter), 0);
+        assert_eq!(sawtooth(u32::MAX), LEVEL_ONE - 1);
+        assert_eq!(square(0, 0), -LEVEL_ONE);
+        assert_eq!(square(0, 1), LEVEL_ONE);
+        assert_eq!(square(u32::MAX, 1 << 32), LEVEL_ONE);
+    }
+
+    #[test]
+    fn samples_match_resolution() {
+        let sine = Waveform::sine();
+        assert_eq!(sine.sample::<Resolution12Bit>(1 << 30), 4095);
+        assert_eq!(sine.sample::<Resolution10Bit>(1 << 30), 1023);
+        assert_eq!(sine.sample::<Resolution8Bit>(1 << 30), 255);
+        assert_eq!(sine.sample::<Resolution8Bit>(3 << 30), 0);
+        assert_eq!(sine.sample::<Resolution8Bit>(0), 128);
+    }
+
+    #[test]
+    fn amplitude_and_offset_are_applied() {
+        let triangle = Waveform::triangle().amplitude(0.1).offset(0.2);
+        let values: [u16; 4] = [0, 1, 2, 3].map(|i| triangle.sample::<Resolution8Bit>(i << 30));
+        assert_eq!(values, [51, 77, 51, 26]);
+    }
+
+    #[test]
+    fn out_of_range_is_clipped() {
+        let sawtooth = Waveform::sawtooth().amplitude(1.0);
+        let values: [u16; 4] = [0, 1, 2, 3].map(|i| sawtooth.sample::<Resolution10Bit>(i << 30));
+        assert_eq!(values, [0, 0, 512, 1023]);
+    }
+
+    #[test]
+    fn square_duty_is_applied() {
+        let values: [u16; 4] =
+            [0, 1, 2, 3].map(|i| Waveform::square(0.5).sample::<Resolution8Bit>(i << 30));
+        assert_eq!(values, [255, 255, 0, 0]);
+        assert_eq!(Waveform::square(0.0).sample::<Resolution8Bit>(0), 0);
+        assert_eq!(
+            Waveform::square(1.0).sample::<Resolution8Bit>(u32::MAX),
+            255
+        );
+    }
+
+    #[test]
+    fn samples_cover_one_period() {
+        let samples = Waveform::sawtooth().samples::<Resolution8Bit>(4);
+        assert_eq!(samples.len(), 4);
+        let mut values = [0; 4];
+        for (value, sample) in values.iter_mut().zip(samples) {
+            *value = sample;
+        }
+        assert_eq!(values, [0, 64, 128, 191]);
+    }
+
+    #[test]
+    fn can_fill_table() {
+        let mut table = [0; 4];
+        Waveform::sawtooth().fill::<Resolution8Bit>(&mut table);
+        assert_eq!(table, [0, 64, 128, 191]);
+    }
+
+    fn assert_table_matches<RES: Resolution>(table: &[u16], waveform: Waveform) {
+        let samples = waveform.samples::<RES>(table.len() as u32);
+        for (value, sample) in table.iter().zip(samples) {
+            assert!((i32::from(*value) - i32::from(sample)).abs() <= 1);
+        }
+    }
+
+    #[test]
+    fn tables_match_samples() {
+        const SINE: [u16; 100] = sine_table(12);
+        const TRIANGLE: [u16; 100] = triangle_table(10);
+        const SAWTOOTH: [u16; 100] = sawtooth_table(8);
+        assert_table_matches::<Resolution12Bit>(&SINE, Waveform::sine());
+        assert_table_matches::<Resolution10Bit>(&TRIANGLE, Waveform::triangle());
+        assert_table_matches::<Resolution8Bit>(&SAWTOOTH, Waveform::sawtooth());
+    }
+
+    #[test]
+    fn can_create_square_table() {
+        assert_eq!(square_table::<5>(8, 2), [255, 255, 0, 0, 0]);
+        assert_eq!(square_table::<2>(10, 3), [1023, 1023]);
+    }
+
+    #[test]
+    fn frames_match_commands() {
+        const VALUES: [u16; 3] = [0, 0x2AA, 0x3FF];
+        let cmd = Command::new()
+            .channel(Channel::Ch1)
+            .buffered()
+            .double_gain();
+        let frames = frames(cmd, &VALUES, 10);
+        for (frame, value) in frames.iter().zip(VALUES.iter()) {
+            let expected = cmd.value(*value).encode::<Resolution10Bit, (), ()>();
+            assert_eq!(*frame, expected);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn frames_reject_values_out_of_range() {
+        frames(Command::new(), &[256], 8);
+    }
+
+    #[test]
+    fn no_samples_for_zero_count() {
+        assert_eq!(Waveform::sine().samples::<Resolution12Bit>(0).next(), None);
+    }
+}
@@ -0,0 +1,58 @@
+#![cfg(feature = "async")]
+
+use embedded_hal_mock::eh1::spi::{Mock as SpiMock, Transaction as SpiTrans};
+use mcp49xx::{asynch::Mcp49xx, Channel, Command, Error};
+
+fn write_transactions(payload: &[u8]) -> [SpiTrans<u8>; 3] {
+    [
+        SpiTrans::transaction_start(),
+        SpiTrans::write_vec(payload.to_vec()),
+        SpiTrans::transaction_end(),
+    ]
+}
+
+#[tokio::test]
+async fn can_send_value() {
+    let trans = write_transactions(&[0b0011_1010, 0b1010_1011]);
+    let mut dev = Mcp49xx::new_mcp4921(SpiMock::new(&trans));
+    dev.send(Command::default().value(0b1010_1010_1011))
+        .await
+        .unwrap();
+    dev.destroy().done();
+}
+
+#[tokio::test]
+async fn can_send_buffered_channel1() {
+    let trans = write_transactions(&[0b1111_0000, 0b0000_1100]);
+    let mut dev = Mcp49xx::new_mcp4912(SpiMock::new(&trans));
+    let cmd = Command::default()
+        .channel(Channel::Ch1)
+        .buffered()
+        .value(0b11);
+    dev.send(cmd).await.unwrap();
+    dev.destroy().done();
+}
+
+#[tokio::test]
+async fn cannot_send_invalid_channel() {
+    let mut dev = Mcp49xx::new_mcp4801(SpiMock::<u8>::new(&[]));
+    let result = dev.send(Command::default().channel(Channel::Ch1)).await;
+    assert_eq!(result, Err(Error::InvalidChannel));
+    dev.destroy().done();
+}
+
+#[tokio::test]
+async fn cannot_send_invalid_value() {
+    let mut dev = Mcp49xx::new_mcp4811(SpiMock::<u8>::new(&[]));
+    let result = dev.send(Command::default().value(1 << 10)).await;
+    assert_eq!(result, Err(Error::InvalidValue));
+    dev.destroy().done();
+}
+
+#[tokio::test]
+async fn cannot_send_buffered() {
+    let mut dev = Mcp49xx::new_mcp4822(SpiMock::<u8>::new(&[]));
+    let result = dev.send(Command::default().buffered()).await;
+    assert_eq!(result, Err(Error::BufferingNotSupported));
+    dev.destroy().done();
+}
@@ -1,21 +1,42 @@
-use embedded_hal_mock::pin::{Mock as PinMock, State as PinState, Transaction as PinTrans};
-use embedded_hal_mock::spi::{Mock as SpiMock, Transaction as SpiTrans};
+use embedded_hal::spi::{ErrorKind, ErrorType, Operation, SpiDevice};
+use embedded_hal_mock::eh1::spi::{Mock as SpiMock, Transaction as SpiTrans};
 use mcp49xx::{marker, Mcp49xx};
 
+/// SPI device where every transaction fails
+pub struct FailingSpi;
+
+impl ErrorType for FailingSpi {
+    type Error = ErrorKind;
+}
+
+impl SpiDevice for FailingSpi {
+    fn transaction(&mut self, _operations: &mut [Operation<'_, u8>]) -> Result<(), ErrorKind> {
+        Err(ErrorKind::Other)
+    }
+}
+
 macro_rules! device_support {
     ($create:ident, $resolution:ident, $channels:ident, $buffering:ident) => {
         pub fn $create(
-            transactions: &[SpiTrans],
-        ) -> Mcp49xx<PinMock, SpiMock, marker::$resolution, marker::$channels, marker::$buffering> {
-            let pin_transactions: Vec<PinTrans> = transactions
-                .iter()
-                .flat_map(|_| [PinTrans::set(PinState::Low), PinTrans::set(PinState::High)])
-                .collect();
-            Mcp49xx::$create(PinMock::new(&pin_transactions))
+            transactions: &[SpiTrans<u8>],
+        ) -> Mcp49xx<SpiMock<u8>, marker::$resolution, marker::$channels, marker::$buffering> {
+            Mcp49xx::$create(SpiMock::new(transactions))
         }
     };
 }
 
+pub fn write_transactions(payload: &[u8]) -> [SpiTrans<u8>; 3] {
+    [
+        SpiTrans::transaction_start(),
+        SpiTrans::write_vec(payload.to_vec()),
+        SpiTrans::transaction_end(),
+    ]
+}
+
+pub fn destroy<RES, CH, BUF>(dev: Mcp49xx<SpiMock<u8>, RES, CH, BUF>) {
+    dev.destroy().done();
+}
+
 device_support!(new_mcp4921, Resolution12Bit, SingleChannel, Buffered);
 device_support!(new_mcp4911, Resolution10Bit, SingleChannel, Buffered);
 device_support!(new_mcp4901, Resolution8Bit, SingleChannel, Buffered);
@@ -0,0 +1,56 @@
+#![cfg(feature = "eh02")]
+
+use embedded_hal_mock::eh0::digital::{
+    Mock as PinMock, State as PinState, Transaction as PinTrans,
+};
+use embedded_hal_mock::eh0::spi::{Mock as SpiMock, Transaction as SpiTrans};
+use mcp49xx::{eh02::Mcp49xx, Channel, Command, Error};
+
+fn cs_transactions(count: usize) -> Vec<PinTrans> {
+    (0..count)
+        .flat_map(|_| [PinTrans::set(PinState::Low), PinTrans::set(PinState::High)])
+        .collect()
+}
+
+#[test]
+fn can_send_value() {
+    let mut spi = SpiMock::new(&[SpiTrans::write(vec![0b0011_1010, 0b1010_1011])]);
+    let mut dev = Mcp49xx::new_mcp4921(PinMock::new(&cs_transactions(1)));
+    dev.send(&mut spi, Command::default().value(0b1010_1010_1011))
+        .unwrap();
+    dev.destroy().done();
+    spi.done();
+}
+
+#[test]
+fn can_send_buffered_channel1() {
+    let mut spi = SpiMock::new(&[SpiTrans::write(vec![0b1111_0000, 0b0000_1100])]);
+    let mut dev = Mcp49xx::new_mcp4912(PinMock::new(&cs_transactions(1)));
+    let cmd = Command::default()
+        .channel(Channel::Ch1)
+        .buffered()
+        .value(0b11);
+    dev.send(&mut spi, cmd).unwrap();
+    dev.destroy().done();
+    spi.done();
+}
+
+#[test]
+fn cannot_send_invalid_channel() {
+    let mut spi = SpiMock::new(&[]);
+    let mut dev = Mcp49xx::new_mcp4801(PinMock::new(&[]));
+    let result = dev.send(&mut spi, Command::default().channel(Channel::Ch1));
+    assert_eq!(result, Err(Error::InvalidChannel));
+    dev.destroy().done();
+    spi.done();
+}
+
+#[test]
+fn cannot_send_buffered() {
+    let mut spi = SpiMock::new(&[]);
+    let mut dev = Mcp49xx::new_mcp4822(PinMock::new(&[]));
+    let result = dev.send(&mut spi, Command::default().buffered());
+    assert_eq!(result, Err(Error::BufferingNotSupported));
+    dev.destroy().done();
+    spi.done();
+}
@@ -1,23 +1,33 @@
-use embedded_hal_mock::spi::{Mock as SpiMock, Transaction as SpiTrans};
-use mcp49xx::{Channel, Command, Error};
+use embedded_hal::spi::ErrorKind;
+use embedded_hal_mock::eh1::delay::{CheckedDelay, Transaction as DelayTrans};
+use embedded_hal_mock::eh1::digital::{
+    Mock as PinMock, State as PinState, Transaction as PinTrans,
+};
+use embedded_hal_mock::eh1::spi::Mock as SpiMock;
+use embedded_hal_mock::eh1::MockError;
+use mcp49xx::{
+    dds::Dds,
+    marker::{Resolution12Bit, Resolution8Bit},
+    waveform,
+    waveform::Waveform,
+    AnyMcp49xx, Calibration, Ch0, Ch1, Channel, Code, Command, DeviceKind, Error, Mcp49xx,
+    Normalized, PowerState, Resolution, SharedMcp49xx, ValuePolicy,
+};
 mod base;
 use crate::base::{
-    new_mcp4801, new_mcp4802, new_mcp4811, new_mcp4812, new_mcp4821, new_mcp4822, new_mcp4901,
-    new_mcp4902, new_mcp4911, new_mcp4912, new_mcp4921, new_mcp4922,
+    destroy, new_mcp4801, new_mcp4802, new_mcp4811, new_mcp4812, new_mcp4821, new_mcp4822,
+    new_mcp4901, new_mcp4902, new_mcp4911, new_mcp4912, new_mcp4921, new_mcp4922,
+    write_transactions, FailingSpi,
 };
 
 macro_rules! test {
     ($name:ident, $create:ident, $cmd:expr, $value:expr ) => {
         #[test]
         fn $name() {
-            let trans = [SpiTrans::write(vec![
-                ($value >> 8) as u8,
-                ($value & 0xff) as u8,
-            ])];
-            let mut spi = SpiMock::new(&trans);
+            let trans = write_transactions(&[($value >> 8) as u8, ($value & 0xff) as u8]);
             let mut dev = $create(&trans);
-            dev.send(&mut spi, $cmd).unwrap();
-            dev.destroy().done();
+            dev.send($cmd).unwrap();
+            destroy(dev);
         }
     };
 }
@@ -44,6 +54,15 @@ fn can_fail() {
     assert_error!(result, InvalidValue);
 }
 
+#[test]
+fn error_maps_spi_error_kind() {
+    use embedded_hal::spi::{Error as _, ErrorKind};
+    let error: Error<ErrorKind> = Error::Comm(ErrorKind::ModeFault);
+    assert_eq!(error.kind(), ErrorKind::ModeFault);
+    let error: Error<ErrorKind> = Error::InvalidValue;
+    assert_eq!(error.kind(), ErrorKind::Other);
+}
+
 macro_rules! common {
     ($name:ident, $create:ident) => {
         mod $name {
@@ -114,13 +133,12 @@ macro_rules! invalid_value_test {
             use super::*;
             #[test]
             fn cannot_send_invalid_value() {
-                let mut spi = SpiMock::new(&[]);
                 let mut dev = $create(&[]);
                 assert_error!(
-                    dev.send(&mut spi, Command::default().value($too_big_value)),
+                    dev.send(Command::default().value($too_big_value)),
                     InvalidValue
                 );
-                dev.destroy().done();
+                destroy(dev);
             }
         }
     };
@@ -136,13 +154,12 @@ macro_rules! invalid_channel_test {
             use super::*;
             #[test]
             fn cannot_send_invalid_channel() {
-                let mut spi = SpiMock::new(&[]);
                 let mut dev = $create(&[]);
                 assert_error!(
-                    dev.send(&mut spi, Command::default().channel(Channel::Ch1)),
+                    dev.send(Command::default().channel(Channel::Ch1)),
                     InvalidChannel
                 );
-                dev.destroy().done();
+                destroy(dev);
             }
         }
     };
@@ -172,13 +189,12 @@ macro_rules! invalid_buffering_test {
             use super::*;
             #[test]
             fn cannot_send_buffered() {
-                let mut spi = SpiMock::new(&[]);
                 let mut dev = $create(&[]);
                 assert_error!(
-                    dev.send(&mut spi, Command::default().buffered()),
+                    dev.send(Command::default().buffered()),
                     BufferingNotSupported
                 );
-                dev.destroy().done();
+                destroy(dev);
             }
         }
     };
@@ -201,3 +217,1008 @@ macro_rules! send_buffered_test {
 }
 
 for_all_ics_with_buffering!(send_buffered_test);
+
+macro_rules! send_synchronized_test {
+    ($name:ident, $create:ident) => {
+        mod $name {
+            use super::*;
+
+            #[test]
+            fn can_send_synchronized() {
+                let mut trans = write_transactions(&[0b0011_0000, 0]).to_vec();
+                trans.extend(write_transactions(&[0b1010_0000, 0]));
+                let ldac =
+                    PinMock::new(&[PinTrans::set(PinState::High), PinTrans::set(PinState::Low)]);
+                let mut delay =
+                    CheckedDelay::new(&[DelayTrans::delay_ns(40), DelayTrans::delay_ns(100)]);
+                let mut dev = $create(&trans).with_ldac(ldac);
+                dev.send_synchronized(
+                    Command::default(),
+                    Command::default().channel(Channel::Ch0).shutdown(),
+                    &mut delay,
+                )
+                .unwrap();
+                let (dev, mut ldac) = dev.release_ldac();
+                ldac.done();
+                delay.done();
+                destroy(dev);
+            }
+
+            #[test]
+            fn cannot_send_synchronized_invalid_value() {
+                let mut delay = CheckedDelay::new(&[]);
+                let mut dev = $create(&[]).with_ldac(PinMock::new(&[]));
+                assert_error!(
+                    dev.send_synchronized(
+                        Command::default(),
+                        Command::default().value(1 << 12),
+                        &mut delay
+                    ),
+                    InvalidValue
+                );
+                let (dev, mut ldac) = dev.release_ldac();
+                ldac.done();
+                delay.done();
+                destroy(dev);
+            }
+        }
+    };
+}
+
+for_all_dual_channel_ics!(send_synchronized, send_synchronized_test);
+
+#[test]
+fn power_state_is_unknown_initially() {
+    let dev = new_mcp4922(&[]).with_shdn(PinMock::new(&[]));
+    assert_eq!(dev.power_state(), None);
+    let (dev, mut shdn) = dev.release_shdn();
+    shdn.done();
+    destroy(dev);
+}
+
+#[test]
+fn can_shutdown_and_wake_through_shdn_pin() {
+    let mut dev = new_mcp4801(&[]).with_shdn(PinMock::new(&[
+        PinTrans::set(PinState::Low),
+        PinTrans::set(PinState::High),
+    ]));
+    dev.hardware_shutdown().unwrap();
+    assert_eq!(dev.power_state(), Some(PowerState::Shutdown));
+    dev.hardware_wake().unwrap();
+    assert_eq!(dev.power_state(), Some(PowerState::Active));
+    let (dev, mut shdn) = dev.release_shdn();
+    shdn.done();
+    destroy(dev);
+}
+
+#[test]
+fn can_send_during_hardware_shutdown() {
+    let trans = write_transactions(&[0b0011_0000, 0]);
+    let mut dev = new_mcp4922(&trans).with_shdn(PinMock::new(&[PinTrans::set(PinState::Low)]));
+    dev.hardware_shutdown().unwrap();
+    dev.send(Command::default()).unwrap();
+    assert_eq!(dev.power_state(), Some(PowerState::Shutdown));
+    let (dev, mut shdn) = dev.release_shdn();
+    shdn.done();
+    destroy(dev);
+}
+
+#[test]
+fn power_state_is_unknown_after_pin_error() {
+    let error = MockError::Io(std::io::ErrorKind::Other);
+    let mut dev = new_mcp4912(&[]).with_shdn(PinMock::new(&[
+        PinTrans::set(PinState::Low),
+        PinTrans::set(PinState::High).with_error(error.clone()),
+    ]));
+    dev.hardware_shutdown().unwrap();
+    assert_eq!(dev.hardware_wake(), Err(Error::Pin(error)));
+    assert_eq!(dev.power_state(), None);
+    let (dev, mut shdn) = dev.release_shdn();
+    shdn.done();
+    destroy(dev);
+}
+
+#[test]
+fn current_is_unknown_initially() {
+    let dev = new_mcp4922(&[]);
+    assert_eq!(dev.current(Channel::Ch0), None);
+    assert_eq!(dev.current(Channel::Ch1), None);
+    destroy(dev);
+}
+
+#[test]
+fn current_tracks_sent_commands() {
+    let mut trans = write_transactions(&[0b1011_0100, 0]).to_vec();
+    trans.extend(write_transactions(&[0b0010_0000, 0]));
+    let mut dev = new_mcp4822(&trans);
+    let cmd = Command::default().channel(Channel::Ch1).value(1024);
+    dev.send(cmd).unwrap();
+    assert_eq!(dev.current(Channel::Ch0), None);
+    assert_eq!(dev.current(Channel::Ch1), Some(cmd));
+    let cmd0 = Command::default().shutdown();
+    dev.send(cmd0).unwrap();
+    assert_eq!(dev.current(Channel::Ch0), Some(cmd0));
+    assert_eq!(dev.current(Channel::Ch1), Some(cmd));
+    destroy(dev);
+}
+
+#[test]
+fn current_is_kept_after_invalid_command() {
+    let trans = write_transactions(&[0b0011_0100, 0]);
+    let mut dev = new_mcp4821(&trans);
+    let cmd = Command::default().value(1024);
+    dev.send(cmd).unwrap();
+    assert_error!(dev.send(cmd.value(1 << 12)), InvalidValue);
+    assert_eq!(dev.current(Channel::Ch0), Some(cmd));
+    destroy(dev);
+}
+
+#[test]
+fn current_is_unknown_after_comm_error() {
+    let mut dev = Mcp49xx::new_mcp4921(FailingSpi);
+    let result = dev.send(Command::default());
+    assert_eq!(result, Err(Error::Comm(ErrorKind::Other)));
+    assert_eq!(dev.current(Channel::Ch0), None);
+}
+
+#[test]
+fn current_tracks_synchronized_commands() {
+    let mut trans = write_transactions(&[0b0011_0000, 0]).to_vec();
+    trans.extend(write_transactions(&[0b1011_0000, 0b0001_0000]));
+    let ldac = PinMock::new(&[PinTrans::set(PinState::High), PinTrans::set(PinState::Low)]);
+    let mut delay = CheckedDelay::new(&[DelayTrans::delay_ns(40), DelayTrans::delay_ns(100)]);
+    let mut dev = new_mcp4902(&trans).with_ldac(ldac);
+    let cmd1 = Command::default().value(1);
+    dev.send_synchronized(Command::default(), cmd1, &mut delay)
+        .unwrap();
+    assert_eq!(dev.current(Channel::Ch0), Some(Command::default()));
+    assert_eq!(dev.current(Channel::Ch1), Some(cmd1.channel(Channel::Ch1)));
+    let (dev, mut ldac) = dev.release_ldac();
+    ldac.done();
+    delay.done();
+    destroy(dev);
+}
+
+#[test]
+fn current_is_unknown_after_synchronized_pin_error() {
+    let error = MockError::Io(std::io::ErrorKind::Other);
+    let mut trans = write_transactions(&[0b0011_0000, 0]).to_vec();
+    trans.extend(write_transactions(&[0b1011_0000, 0]));
+    let ldac = PinMock::new(&[
+        PinTrans::set(PinState::High),
+        PinTrans::set(PinState::Low).with_error(error.clone()),
+    ]);
+    let mut delay = CheckedDelay::new(&[DelayTrans::delay_ns(40)]);
+    let mut dev = new_mcp4922(&trans).with_ldac(ldac);
+    let result = dev.send_synchronized(Command::default(), Command::default(), &mut delay);
+    assert_eq!(result, Err(Error::Pin(error)));
+    assert_eq!(dev.current(Channel::Ch0), None);
+    assert_eq!(dev.current(Channel::Ch1), None);
+    let (dev, mut ldac) = dev.release_ldac();
+    ldac.done();
+    delay.done();
+    destroy(dev);
+}
+
+#[test]
+fn ldac_is_low_after_synchronized_comm_error() {
+    let ldac = PinMock::new(&[PinTrans::set(PinState::High), PinTrans::set(PinState::Low)]);
+    let mut delay = CheckedDelay::new(&[]);
+    let mut dev = Mcp49xx::new_mcp4922(FailingSpi).with_ldac(ldac);
+    let result = dev.send_synchronized(Command::default(), Command::default(), &mut delay);
+    assert_eq!(result, Err(Error::Comm(ErrorKind::Other)));
+    assert_eq!(dev.current(Channel::Ch0), None);
+    assert_eq!(dev.current(Channel::Ch1), None);
+    let (_, mut ldac) = dev.release_ldac();
+    ldac.done();
+    delay.done();
+}
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() < 1e-5,
+        "{} is not close to {}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn mcp48xx_uses_internal_reference() {
+    let dev = new_mcp4812(&[]);
+    assert_eq!(dev.vref(Channel::Ch0), Some(2.048));
+    assert_eq!(dev.vref(Channel::Ch1), Some(2.048));
+    destroy(dev);
+}
+
+#[test]
+fn mcp49xx_reference_can_be_set() {
+    let mut dev = new_mcp4911(&[]);
+    assert_eq!(dev.vref(Channel::Ch0), None);
+    dev.set_vref(3.3);
+    assert_eq!(dev.vref(Channel::Ch0), Some(3.3));
+    destroy(dev);
+}
+
+#[test]
+fn cannot_set_voltage_without_reference() {
+    let mut dev = new_mcp4921(&[]);
+    assert_error!(dev.set_voltage(Channel::Ch0, 1.0), NoReference);
+    destroy(dev);
+}
+
+#[test]
+fn can_set_voltage_with_internal_reference() {
+    let trans = write_transactions(&[0b0011_1000, 0]);
+    let mut dev = new_mcp4822(&trans);
+    assert_eq!(dev.set_voltage(Channel::Ch0, 1.024), Ok(1.024));
+    destroy(dev);
+}
+
+#[test]
+fn can_set_voltage_with_external_reference() {
+    let trans = write_transactions(&[0b1011_1000, 0]);
+    let mut dev = new_mcp4902(&trans);
+    dev.set_vref(2.5);
+    assert_eq!(dev.set_voltage(Channel::Ch1, 1.25), Ok(1.25));
+    destroy(dev);
+}
+
+#[test]
+fn returns_achieved_voltage() {
+    let trans = write_transactions(&[0b0011_0000, 0b0100_0000]);
+    let mut dev = new_mcp4801(&trans);
+    assert_eq!(dev.set_voltage(Channel::Ch0, 0.03), Ok(0.032));
+    destroy(dev);
+}
+
+#[test]
+fn can_send_voltage_with_double_gain() {
+    let trans = write_transactions(&[0b0001_1011, 0b1011_1000]);
+    let mut dev = new_mcp4821(&trans);
+    let cmd = Command::default().double_gain();
+    assert_close(dev.send_voltage(cmd, 3.0).unwrap(), 3.0);
+    destroy(dev);
+}
+
+#[test]
+fn set_voltage_keeps_gain_and_enables_channel() {
+    let mut trans = write_transactions(&[0b1000_0000, 0]).to_vec();
+    trans.extend(write_transactions(&[0b1001_1011, 0b1011_1000]));
+    let mut dev = new_mcp4822(&trans);
+    let cmd = Command::default()
+        .channel(Channel::Ch1)
+        .double_gain()
+        .shutdown();
+    dev.send(cmd).unwrap();
+    assert_close(dev.set_voltage(Channel::Ch1, 3.0).unwrap(), 3.0);
+    assert_eq!(dev.current(Channel::Ch1), Some(cmd.enable().value(3000)));
+    destroy(dev);
+}
+
+#[test]
+fn cannot_set_voltage_out_of_range() {
+    let mut dev = new_mcp4912(&[]);
+    dev.set_vref(2.0);
+    assert_error!(dev.set_voltage(Channel::Ch0, 2.5), InvalidValue);
+    assert_error!(dev.set_voltage(Channel::Ch0, -0.5), InvalidValue);
+    destroy(dev);
+}
+
+#[test]
+fn mcp49x2_references_can_be_set_per_channel() {
+    let mut dev = new_mcp4922(&[]);
+    dev.set_channel_vref(Channel::Ch1, 4.096);
+    assert_eq!(dev.vref(Channel::Ch0), None);
+    assert_eq!(dev.vref(Channel::Ch1), Some(4.096));
+    dev.set_vref(2.5);
+    dev.set_channel_vref(Channel::Ch0, 1.024);
+    assert_eq!(dev.vref(Channel::Ch0), Some(1.024));
+    assert_eq!(dev.vref(Channel::Ch1), Some(2.5));
+    destroy(dev);
+}
+
+#[test]
+fn set_voltage_uses_channel_reference() {
+    let mut trans = write_transactions(&[0b0011_1000, 0]).to_vec();
+    trans.extend(write_transactions(&[0b1011_1000, 0]));
+    let mut dev = new_mcp4912(&trans);
+    dev.set_channel_vref(Channel::Ch0, 2.048);
+    dev.set_channel_vref(Channel::Ch1, 4.096);
+    assert_close(dev.set_voltage(Channel::Ch0, 1.024).unwrap(), 1.024);
+    assert_close(dev.set_voltage(Channel::Ch1, 2.048).unwrap(), 2.048);
+    destroy(dev);
+}
+
+#[test]
+fn cannot_set_voltage_without_channel_reference() {
+    let mut dev = new_mcp4902(&[]);
+    dev.set_channel_vref(Channel::Ch0, 2.048);
+    assert_error!(dev.set_voltage(Channel::Ch1, 1.0), NoReference);
+    destroy(dev);
+}
+
+#[test]
+fn auto_gain_selects_single_gain_within_range() {
+    let trans = write_transactions(&[0b0011_1011, 0b1011_1000]);
+    let mut dev = new_mcp4921(&trans);
+    dev.set_vref(4.096);
+    assert_close(dev.set_voltage_auto_gain(Channel::Ch0, 3.0).unwrap(), 3.0);
+    destroy(dev);
+}
+
+#[test]
+fn auto_gain_selects_double_gain_above_range() {
+    let trans = write_transactions(&[0b1001_1011, 0b1011_1000]);
+    let mut dev = new_mcp4822(&trans);
+    dev.set_vdd(5.0, 0.04);
+    assert_close(dev.set_voltage_auto_gain(Channel::Ch1, 3.0).unwrap(), 3.0);
+    assert!(dev.current(Channel::Ch1).unwrap().is_double_gain());
+    destroy(dev);
+}
+
+#[test]
+fn auto_gain_keeps_buffering_and_enables_channel() {
+    let mut trans = write_transactions(&[0b0100_0000, 0]).to_vec();
+    trans.extend(write_transactions(&[0b0111_1000, 0]));
+    let mut dev = new_mcp4901(&trans);
+    dev.set_vref(2.048);
+    dev.send(Command::default().buffered().double_gain().shutdown())
+        .unwrap();
+    assert_close(
+        dev.set_voltage_auto_gain(Channel::Ch0, 1.024).unwrap(),
+        1.024,
+    );
+    destroy(dev);
+}
+
+#[test]
+fn auto_gain_rejects_voltage_above_vdd_headroom() {
+    let mut dev = new_mcp4912(&[]);
+    dev.set_vref(2.5);
+    dev.set_vdd(3.3, 0.04);
+    assert_error!(
+        dev.set_voltage_auto_gain(Channel::Ch1, 3.29),
+        VddHeadroomExceeded
+    );
+    assert_eq!(dev.current(Channel::Ch1), None);
+    destroy(dev);
+}
+
+#[test]
+fn double_gain_rejects_voltage_above_vdd_headroom() {
+    let mut dev = new_mcp4811(&[]);
+    dev.set_vdd(2.7, 0.1);
+    assert_error!(
+        dev.send_voltage(Command::default().double_gain(), 2.65),
+        VddHeadroomExceeded
+    );
+    destroy(dev);
+}
+
+#[test]
+fn auto_gain_rejects_voltage_out_of_range() {
+    let mut dev = new_mcp4802(&[]);
+    assert_error!(dev.set_voltage_auto_gain(Channel::Ch0, 4.2), InvalidValue);
+    destroy(dev);
+}
+
+#[test]
+fn send_applies_channel_calibration() {
+    let mut trans = write_transactions(&[0b1011_0000, 0b0110_0110]).to_vec();
+    trans.extend(write_transactions(&[0b0011_0000, 0b0110_0100]));
+    let mut dev = new_mcp4922(&trans);
+    let cal = Calibration::new(2.0, 1.0);
+    dev.set_calibration(Channel::Ch1, cal);
+    assert_eq!(dev.calibration(Channel::Ch1), Some(cal));
+    assert_eq!(dev.calibration(Channel::Ch0), None);
+    let cmd = Command::default().channel(Channel::Ch1).value(100);
+    dev.send(cmd).unwrap();
+    assert_eq!(dev.current(Channel::Ch1), Some(cmd));
+    dev.send(Command::default().value(100)).unwrap();
+    destroy(dev);
+}
+
+#[test]
+fn calibration_clamps_to_max_code() {
+    let trans = write_transactions(&[0b0011_1111, 0b1111_0000]);
+    let mut dev = new_mcp4801(&trans);
+    dev.set_calibration(Channel::Ch0, Calibration::new(10.0, 1.0));
+    dev.send(Command::default().value(250)).unwrap();
+    destroy(dev);
+}
+
+#[test]
+fn calibration_does_not_accept_invalid_values() {
+    let mut dev = new_mcp4811(&[]);
+    dev.set_calibration(Channel::Ch0, Calibration::new(0.0, 0.5));
+    assert_error!(dev.send(Command::default().value(1 << 10)), InvalidValue);
+    destroy(dev);
+}
+
+#[test]
+fn calibration_can_be_cleared() {
+    let trans = write_transactions(&[0b0011_0000, 0b0110_0100]);
+    let mut dev = new_mcp4921(&trans);
+    dev.set_calibration(Channel::Ch0, Calibration::new(2.0, 1.0));
+    dev.clear_calibration(Channel::Ch0);
+    assert_eq!(dev.calibration(Channel::Ch0), None);
+    dev.send(Command::default().value(100)).unwrap();
+    destroy(dev);
+}
+
+#[test]
+fn set_voltage_applies_calibration() {
+    let trans = write_transactions(&[0b0011_0111, 0b1111_1011]);
+    let mut dev = new_mcp4821(&trans);
+    dev.set_calibration(Channel::Ch0, Calibration::new(-5.0, 1.0));
+    assert_close(dev.set_voltage(Channel::Ch0, 1.024).unwrap(), 1.024);
+    destroy(dev);
+}
+
+#[test]
+fn send_synchronized_applies_calibration() {
+    let mut trans = write_transactions(&[0b0011_0000, 0b0000_0001]).to_vec();
+    trans.extend(write_transactions(&[0b1011_0000, 0b0000_0000]));
+    let ldac = PinMock::new(&[PinTrans::set(PinState::High), PinTrans::set(PinState::Low)]);
+    let mut delay = CheckedDelay::new(&[DelayTrans::delay_ns(40), DelayTrans::delay_ns(100)]);
+    let mut dev = new_mcp4822(&trans).with_ldac(ldac);
+    dev.set_calibration(Channel::Ch0, Calibration::new(1.0, 1.0));
+    dev.set_calibration(Channel::Ch1, Calibration::new(-1.0, 1.0));
+    dev.send_synchronized(Command::default(), Command::default(), &mut delay)
+        .unwrap();
+    let (dev, mut ldac) = dev.release_ldac();
+    ldac.done();
+    delay.done();
+    destroy(dev);
+}
+
+#[test]
+fn can_set_calibration_data() {
+    let mut dev = new_mcp4922(&[]);
+    let cal = Calibration::new(1.0, 0.99);
+    dev.set_calibration(Channel::Ch1, cal);
+    dev.set_channel_vref(Channel::Ch0, 2.5);
+    let data = dev.calibration_data();
+    assert_eq!(data.calibration, [None, Some(cal)]);
+    assert_eq!(data.vref, [Some(2.5), None]);
+
+    let mut other = new_mcp4912(&[]);
+    other.set_calibration_data(data);
+    assert_eq!(other.calibration(Channel::Ch1), Some(cal));
+    assert_eq!(other.vref(Channel::Ch0), Some(2.5));
+    destroy(dev);
+    destroy(other);
+}
+
+#[test]
+fn can_send_samples() {
+    let mut trans = write_transactions(&[0b1011_1111, 0b1111_0000]).to_vec();
+    trans.extend(write_transactions(&[0b1011_0000, 0b0000_0000]));
+    let mut delay = CheckedDelay::new(&[DelayTrans::delay_ns(500), DelayTrans::delay_ns(500)]);
+    let mut dev = new_mcp4802(&trans);
+    let square = Waveform::square(0.5).samples::<Resolution8Bit>(2);
+    dev.send_samples(
+        Command::default().channel(Channel::Ch1),
+        square,
+        &mut delay,
+        500,
+    )
+    .unwrap();
+    delay.done();
+    destroy(dev);
+}
+
+#[test]
+fn send_samples_stops_at_error() {
+    let trans = write_transactions(&[0b0011_0000, 0b0001_0000]);
+    let mut delay = CheckedDelay::new(&[DelayTrans::delay_ns(500)]);
+    let mut dev = new_mcp4801(&trans);
+    assert_error!(
+        dev.send_samples(Command::default(), [1, 256, 2], &mut delay, 500),
+        InvalidValue
+    );
+    delay.done();
+    destroy(dev);
+}
+
+#[test]
+fn can_send_dds_samples() {
+    let mut trans = write_transactions(&[0b1011_0000, 0b0000_0000]).to_vec();
+    trans.extend(write_transactions(&[0b1011_1000, 0b0000_0000]));
+    trans.extend(write_transactions(&[0b1011_1111, 0b1111_0000]));
+    let mut dev = new_mcp4802(&trans);
+    let mut dds = Dds::new(&[0, 255], Channel::Ch1).unwrap();
+    dds.set_frequency(1.0, 4.0).unwrap();
+    for _ in 0..3 {
+        dev.send(dds.next_command(Command::default())).unwrap();
+    }
+    destroy(dev);
+}
+
+#[test]
+fn precomputed_frames_match_sent_frames() {
+    const BITS: u8 = <Resolution12Bit as Resolution>::BITS;
+    const CMD: Command = Command::new().channel(Channel::Ch1).buffered();
+    const SINE: [u16; 8] = waveform::sine_table(BITS);
+    const FRAMES: [[u8; 2]; 8] = waveform::frames(CMD, &SINE, BITS);
+    let trans: Vec<_> = FRAMES.iter().flat_map(|f| write_transactions(f)).collect();
+    let mut dev = new_mcp4922(&trans);
+    for value in SINE.iter() {
+        dev.send(CMD.value(*value)).unwrap();
+    }
+    destroy(dev);
+}
+
+#[test]
+fn can_send_prepared_command() {
+    let frame = [0b1111_0100, 0b1101_0010];
+    let mut trans = write_transactions(&frame).to_vec();
+    trans.extend(write_transactions(&frame));
+    let mut dev = new_mcp4922(&trans);
+    let cmd = Command::default()
+        .channel(Channel::Ch1)
+        .buffered()
+        .value(1234);
+    let prepared = dev.prepare(cmd).unwrap();
+    assert_eq!(prepared.command(), cmd);
+    assert_eq!(prepared.frame(), frame);
+    dev.send_prepared(&prepared).unwrap();
+    dev.send_prepared(&prepared).unwrap();
+    assert_eq!(dev.current(Channel::Ch1), Some(cmd));
+    destroy(dev);
+}
+
+#[test]
+fn cannot_prepare_invalid_command() {
+    let dev = new_mcp4801(&[]);
+    assert_error!(dev.prepare(Command::default().value(256)), InvalidValue);
+    assert_error!(
+        dev.prepare(Command::default().channel(Channel::Ch1)),
+        InvalidChannel
+    );
+    assert_error!(
+        dev.prepare(Command::default().buffered()),
+        BufferingNotSupported
+    );
+    destroy(dev);
+}
+
+#[test]
+fn prepare_applies_calibration() {
+    let trans = write_transactions(&[0b0011_0000, 0b0110_0110]);
+    let mut dev = new_mcp4921(&trans);
+    dev.set_calibration(Channel::Ch0, Calibration::new(2.0, 1.0));
+    let prepared = dev.prepare(Command::default().value(100)).unwrap();
+    dev.clear_calibration(Channel::Ch0);
+    dev.send_prepared(&prepared).unwrap();
+    destroy(dev);
+}
+
+#[test]
+fn current_is_unknown_after_prepared_comm_error() {
+    let mut dev = Mcp49xx::new_mcp4921(FailingSpi);
+    let prepared = dev.prepare(Command::default()).unwrap();
+    assert!(matches!(dev.send_prepared(&prepared), Err(Error::Comm(_))));
+    assert_eq!(dev.current(Channel::Ch0), None);
+}
+
+#[test]
+fn can_encode_commands() {
+    let dev = new_mcp4912(&[]);
+    let commands = [
+        Command::default().value(1023),
+        Command::default().channel(Channel::Ch1).buffered().value(1),
+    ];
+    let mut buffer = [0; 5];
+    assert_eq!(dev.encode(commands.iter().copied(), &mut buffer), Ok(4));
+    assert_eq!(
+        buffer,
+        [0b0011_1111, 0b1111_1100, 0b1111_0000, 0b0000_0100, 0]
+    );
+    let mut words = [0; 2];
+    assert_eq!(
+        dev.encode_words(commands.iter().copied(), &mut words),
+        Ok(2)
+    );
+    assert_eq!(words, [0b0011_1111_1111_1100, 0b1111_0000_0000_0100]);
+    destroy(dev);
+}
+
+#[test]
+fn can_encode_values() {
+    let dev = new_mcp4802(&[]);
+    let cmd = Command::default().channel(Channel::Ch1).double_gain();
+    let mut buffer = [0; 4];
+    assert_eq!(dev.encode_values(cmd, &[0xAB, 0x01], &mut buffer), Ok(4));
+    assert_eq!(buffer, [0b1001_1010, 0b1011_0000, 0b1001_0000, 0b0001_0000]);
+    let mut words = [0; 2];
+    assert_eq!(
+        dev.encode_value_words(cmd, &[0xAB, 0x01], &mut words),
+        Ok(2)
+    );
+    assert_eq!(words, [0b1001_1010_1011_0000, 0b1001_0000_0001_0000]);
+    destroy(dev);
+}
+
+#[test]
+fn encoded_frames_match_sent_frames() {
+    let cmd = Command::default().value(100);
+    let mut buffer = [0; 2];
+    let mut dev = new_mcp4821(&[]);
+    dev.set_calibration(Channel::Ch0, Calibration::new(3.0, 1.0));
+    dev.encode([cmd], &mut buffer).unwrap();
+    destroy(dev);
+    let mut dev = new_mcp4821(&write_transactions(&buffer));
+    dev.set_calibration(Channel::Ch0, Calibration::new(3.0, 1.0));
+    dev.send(cmd).unwrap();
+    assert_eq!(buffer, [0b0011_0000, 103]);
+    destroy(dev);
+}
+
+#[test]
+fn cannot_encode_invalid_command() {
+    let dev = new_mcp4811(&[]);
+    let mut buffer = [0; 4];
+    assert_error!(
+        dev.encode_values(Command::default(), &[1, 1024], &mut buffer),
+        InvalidValue
+    );
+    let mut words = [0; 1];
+    assert_error!(
+        dev.encode_words([Command::default().buffered()], &mut words),
+        BufferingNotSupported
+    );
+    destroy(dev);
+}
+
+#[test]
+fn cannot_encode_into_too_small_buffer() {
+    let dev = new_mcp4921(&[]);
+    let mut buffer = [0; 3];
+    assert_error!(
+        dev.encode_values(Command::default(), &[1, 2], &mut buffer),
+        BufferTooSmall
+    );
+    let mut words = [0; 1];
+    assert_error!(
+        dev.encode_value_words(Command::default(), &[1, 2], &mut words),
+        BufferTooSmall
+    );
+    destroy(dev);
+}
+
+fn commands_for_kind_tests() -> Vec<Command> {
+    let mut commands = Vec::new();
+    for value in [0, 255, 256, 1023, 1024, 4095, 4096] {
+        for channel in [Channel::Ch0, Channel::Ch1] {
+            let cmd = Command::default().channel(channel).value(value);
+            commands.push(cmd);
+            commands.push(cmd.buffered().double_gain());
+            commands.push(cmd.shutdown());
+        }
+    }
+    commands
+}
+
+macro_rules! any_test {
+    ($name:ident, $create:ident, $kind:ident) => {
+        #[test]
+        fn $name() {
+            let kind = DeviceKind::$kind;
+            assert_eq!(DeviceKind::from_name(stringify!($name)), Some(kind));
+            let typed = $create(&[]);
+            let mut trans = Vec::new();
+            let mut results = Vec::new();
+            for cmd in commands_for_kind_tests() {
+                let mut frame = [0; 2];
+                let result = typed.encode([cmd], &mut frame).map(|_| ());
+                if result.is_ok() {
+                    trans.extend(write_transactions(&frame));
+                }
+                results.push((cmd, result));
+            }
+            destroy(typed);
+            let mut dev = AnyMcp49xx::new(SpiMock::new(&trans), kind);
+            for (cmd, expected) in results {
+                assert_eq!(dev.send(cmd), expected);
+            }
+            dev.destroy().done();
+        }
+    };
+}
+
+any_test!(mcp4801, new_mcp4801, Mcp4801);
+any_test!(mcp4802, new_mcp4802, Mcp4802);
+any_test!(mcp4811, new_mcp4811, Mcp4811);
+any_test!(mcp4812, new_mcp4812, Mcp4812);
+any_test!(mcp4821, new_mcp4821, Mcp4821);
+any_test!(mcp4822, new_mcp4822, Mcp4822);
+any_test!(mcp4901, new_mcp4901, Mcp4901);
+any_test!(mcp4902, new_mcp4902, Mcp4902);
+any_test!(mcp4911, new_mcp4911, Mcp4911);
+any_test!(mcp4912, new_mcp4912, Mcp4912);
+any_test!(mcp4921, new_mcp4921, Mcp4921);
+any_test!(mcp4922, new_mcp4922, Mcp4922);
+
+#[test]
+fn device_kind_provides_info() {
+    let kind = DeviceKind::Mcp4912;
+    assert_eq!(kind.name(), "MCP4912");
+    assert_eq!(kind.bits(), 10);
+    assert_eq!(kind.channels(), 2);
+    assert!(kind.supports_buffering());
+    assert_eq!(kind.internal_reference(), None);
+    let kind = DeviceKind::Mcp4801;
+    assert_eq!(kind.name(), "MCP4801");
+    assert_eq!(kind.bits(), 8);
+    assert_eq!(kind.channels(), 1);
+    assert!(!kind.supports_buffering());
+    assert_eq!(kind.internal_reference(), Some(2.048));
+    assert_eq!(DeviceKind::from_name("mcp4822"), Some(DeviceKind::Mcp4822));
+    assert_eq!(DeviceKind::from_name("MCP4823"), None);
+}
+
+#[test]
+fn any_device_tracks_current() {
+    let trans = write_transactions(&[0b1011_0000, 0b0000_0000]);
+    let mut dev = AnyMcp49xx::new(SpiMock::new(&trans), DeviceKind::Mcp4822);
+    assert_eq!(dev.kind(), DeviceKind::Mcp4822);
+    let cmd = Command::default().channel(Channel::Ch1);
+    dev.send(cmd).unwrap();
+    assert_eq!(dev.current(Channel::Ch1), Some(cmd));
+    assert_eq!(dev.current(Channel::Ch0), None);
+    dev.destroy().done();
+}
+
+#[test]
+fn can_split_channels() {
+    let mut trans = write_transactions(&[0b1011_0000, 0b0110_0100]).to_vec();
+    trans.extend(write_transactions(&[0b0011_0000, 0b0000_0001]));
+    let mut shared = SharedMcp49xx::new();
+    let (mut ch0, mut ch1) = new_mcp4822(&trans).split(&mut shared);
+    assert_eq!(ch0.channel(), Channel::Ch0);
+    assert_eq!(ch1.channel(), Channel::Ch1);
+    // The channel of the command is ignored
+    ch1.send(Command::default().value(100)).unwrap();
+    ch0.send(Command::default().channel(Channel::Ch1).value(1))
+        .unwrap();
+    assert_eq!(ch0.current(), Some(Command::default().value(1)));
+    assert_eq!(
+        ch1.current(),
+        Some(Command::default().channel(Channel::Ch1).value(100))
+    );
+    let dev = Mcp49xx::join(ch0, ch1);
+    assert_eq!(dev.current(Channel::Ch0), Some(Command::default().value(1)));
+    destroy(dev);
+}
+
+#[test]
+#[should_panic]
+fn cannot_join_channels_of_different_splits() {
+    let mut shared0 = SharedMcp49xx::new();
+    let mut shared1 = SharedMcp49xx::new();
+    let (ch0, _) = new_mcp4822(&[]).split(&mut shared0);
+    let (_, ch1) = new_mcp4822(&[]).split(&mut shared1);
+    Mcp49xx::join(ch0, ch1);
+}
+
+#[cfg(feature = "critical-section")]
+#[test]
+fn can_use_split_channels_from_different_threads() {
+    let mut trans = write_transactions(&[0b0011_0000, 0b0000_0001]).to_vec();
+    trans.extend(write_transactions(&[0b1011_0000, 0b0000_0010]));
+    let shared = Box::leak(Box::new(mcp49xx::SyncSharedMcp49xx::new()));
+    let (mut ch0, mut ch1) = new_mcp4822(&trans).split(shared);
+    let thread = std::thread::spawn(move || {
+        ch0.send(Command::default().value(1)).unwrap();
+        ch0
+    });
+    let ch0 = thread.join().unwrap();
+    let thread = std::thread::spawn(move || {
+        ch1.send(Command::default().value(2)).unwrap();
+        ch1
+    });
+    let ch1 = thread.join().unwrap();
+    destroy(Mcp49xx::join(ch0, ch1));
+}
+
+#[test]
+fn split_channels_check_commands() {
+    let mut shared = SharedMcp49xx::new();
+    let (mut ch0, mut ch1) = new_mcp4812(&[]).split(&mut shared);
+    assert_error!(ch0.send(Command::default().value(1024)), InvalidValue);
+    assert_error!(
+        ch1.send(Command::default().buffered()),
+        BufferingNotSupported
+    );
+    destroy(Mcp49xx::join(ch0, ch1));
+}
+
+#[test]
+fn can_send_to_typed_channel() {
+    let mut trans = write_transactions(&[0b1011_0000, 0b0000_0011]).to_vec();
+    trans.extend(write_transactions(&[0b0011_0000, 0b0000_0100]));
+    let mut dev = new_mcp4822(&trans);
+    dev.send_to(Ch1, Command::default().value(3)).unwrap();
+    dev.send_to(Ch0, Command::default().channel(Channel::Ch1).value(4))
+        .unwrap();
+    destroy(dev);
+}
+
+#[test]
+fn can_send_to_typed_channel_of_single_channel_device() {
+    let trans = write_transactions(&[0b0011_0000, 0b0101_0000]);
+    let mut dev = new_mcp4801(&trans);
+    dev.send_to(Ch0, Command::default().value(5)).unwrap();
+    destroy(dev);
+}
+
+#[test]
+fn can_send_device_command() {
+    let trans = write_transactions(&[0b1101_0000, 0b0000_0101]);
+    let mut dev = new_mcp4922(&trans);
+    let cmd = dev
+        .command()
+        .channel(Channel::Ch1)
+        .buffered()
+        .double_gain()
+        .value(5);
+    assert_eq!(
+        cmd.command(),
+        Command::default()
+            .channel(Channel::Ch1)
+            .buffered()
+            .double_gain()
+            .value(5)
+    );
+    dev.send(cmd.into()).unwrap();
+    destroy(dev);
+}
+
+#[test]
+fn can_send_device_command_to_unbuffered_device() {
+    let trans = write_transactions(&[0b0011_0000, 0b0101_0000]);
+    let mut dev = new_mcp4801(&trans);
+    dev.send(dev.command().value(5).into()).unwrap();
+    destroy(dev);
+}
+
+#[test]
+fn can_send_typed_code() {
+    let mut trans = write_transactions(&[0b0011_1111, 0b1111_1100]).to_vec();
+    trans.extend(write_transactions(&[0b0011_0000, 0b0000_0100]));
+    let mut dev = new_mcp4811(&trans);
+    dev.send_command(dev.command().code(Code::MAX)).unwrap();
+    let code = Code::<10>::new(1).unwrap();
+    dev.send_command(dev.command().code(code)).unwrap();
+    destroy(dev);
+}
+
+#[test]
+fn can_saturate_values() {
+    let trans = write_transactions(&[0b0011_1111, 0b1111_0000]);
+    let mut dev = new_mcp4801(&trans);
+    dev.set_value_policy(ValuePolicy::Saturate);
+    assert_eq!(dev.value_policy(), ValuePolicy::Saturate);
+    dev.send(Command::default().value(300)).unwrap();
+    destroy(dev);
+}
+
+#[test]
+fn can_scale_16_bit_values() {
+    let trans = write_transactions(&[0b0011_1111, 0b1111_1111]);
+    let mut dev = new_mcp4921(&trans);
+    dev.set_value_policy(ValuePolicy::Scale16Bit);
+    dev.send(Command::default().value(0xFFFF)).unwrap();
+    destroy(dev);
+}
+
+#[test]
+fn can_scale_normalized_values() {
+    let trans = write_transactions(&[0b0011_1000, 0b0000_0000]);
+    let mut dev = new_mcp4811(&trans);
+    dev.set_value_policy(ValuePolicy::ScaleNormalized);
+    dev.send(Command::default().value(0x4000)).unwrap();
+    assert_error!(dev.send(Command::default().value(0x8001)), InvalidValue);
+    destroy(dev);
+}
+
+#[test]
+fn value_policy_does_not_affect_voltages() {
+    let trans = write_transactions(&[0b0011_1000, 0b0000_0000]);
+    let mut dev = new_mcp4821(&trans);
+    dev.set_value_policy(ValuePolicy::Scale16Bit);
+    dev.set_voltage(Channel::Ch0, 1.024).unwrap();
+    destroy(dev);
+}
+
+#[test]
+fn value_policy_does_not_affect_typed_codes() {
+    let mut trans = write_transactions(&[0b0011_1111, 0b1111_1111]).to_vec();
+    trans.extend(write_transactions(&[0b0011_1111, 0b1111_1111]));
+    let mut dev = new_mcp4921(&trans);
+    dev.set_value_policy(ValuePolicy::Scale16Bit);
+    dev.send_command(dev.command().code(Code::MAX)).unwrap();
+    // the value policy applies again after setting a value
+    dev.send_command(dev.command().code(Code::MAX).value(0xFFFF))
+        .unwrap();
+    destroy(dev);
+}
+
+#[test]
+fn value_policy_does_not_affect_samples() {
+    let mut trans = write_transactions(&[0b0011_1111, 0b1111_0000]).to_vec();
+    trans.extend(write_transactions(&[0b0011_0000, 0b0000_0000]));
+    let mut delay = CheckedDelay::new(&[DelayTrans::delay_ns(500), DelayTrans::delay_ns(500)]);
+    let mut dev = new_mcp4801(&trans);
+    dev.set_value_policy(ValuePolicy::ScaleNormalized);
+    let square = Waveform::square(0.5).samples::<Resolution8Bit>(2);
+    dev.send_samples(Command::default(), square, &mut delay, 500)
+        .unwrap();
+    delay.done();
+    destroy(dev);
+}
+
+#[test]
+fn value_policy_does_not_affect_dds() {
+    let trans = write_transactions(&[0b0011_0000, 0b0111_1011]);
+    let mut dev = new_mcp4921(&trans);
+    dev.set_value_policy(ValuePolicy::Scale16Bit);
+    let mut dds = Dds::new(&[123], Channel::Ch0).unwrap();
+    dev.send_dds(&mut dds, Command::default()).unwrap();
+    destroy(dev);
+}
+
+#[test]
+fn current_value_is_code_after_value_policy() {
+    let trans = write_transactions(&[0b0011_1000, 0b0000_0000]);
+    let mut dev = new_mcp4921(&trans);
+    dev.set_value_policy(ValuePolicy::Scale16Bit);
+    dev.send(Command::default().value(0x8000)).unwrap();
+    assert_eq!(
+        dev.current(Channel::Ch0),
+        Some(Command::default().value(2048))
+    );
+    destroy(dev);
+}
+
+#[test]
+fn prepared_value_is_code_after_value_policy() {
+    let mut dev = new_mcp4921(&[]);
+    dev.set_value_policy(ValuePolicy::Scale16Bit);
+    let prepared = dev.prepare(Command::default().value(0x8000)).unwrap();
+    assert_eq!(prepared.command(), Command::default().value(2048));
+    destroy(dev);
+}
+
+macro_rules! send_normalized_test {
+    ($name:ident, $create:ident, $msb:expr, $lsb:expr) => {
+        #[test]
+        fn $name() {
+            let trans = write_transactions(&[$msb, $lsb]);
+            let mut dev = $create(&trans);
+            // the value policy does not apply
+            dev.set_value_policy(ValuePolicy::Scale16Bit);
+            let value = Normalized::from_f32(0.75).unwrap();
+            dev.send_normalized(Command::default().value(5), value)
+                .unwrap();
+            destroy(dev);
+        }
+    };
+}
+
+// 0.75 is code 191.25, 767.25 and 3071.25 respectively
+send_normalized_test!(send_normalized_8bit, new_mcp4801, 0b0011_1011, 0b1111_0000);
+send_normalized_test!(send_normalized_10bit, new_mcp4811, 0b0011_1011, 0b1111_1100);
+send_normalized_test!(send_normalized_12bit, new_mcp4821, 0b0011_1011, 0b1111_1111);
+
+#[test]
+fn normalized_value_matches_code_of_resolution() {
+    fn code<RES: Resolution>(value: Normalized) -> u16 {
+        RES::Code::from(value).into()
+    }
+    let value = Normalized::from_fraction(0x2AAB).unwrap();
+    assert_eq!(code::<Resolution8Bit>(value), 85);
+    assert_eq!(code::<Resolution12Bit>(value), 1365);
+}
@@ -0,0 +1,254 @@
+#![cfg(feature = "sim")]
+
+use embedded_hal::digital::OutputPin;
+use embedded_hal::spi::SpiDevice;
+use embedded_hal_mock::eh1::delay::NoopDelay;
+use mcp49xx::{sim::Simulator, Channel, Code, Command, Mcp49xx, ValuePolicy};
+
+fn assert_close(actual: Option<f32>, expected: f32) {
+    let actual = actual.expect("Channel is shut down");
+    assert!(
+        (actual - expected).abs() < 1e-5,
+        "{} != {}",
+        actual,
+        expected
+    );
+}
+
+macro_rules! output_test {
+    ($name:ident, $create:ident, [$($vref:expr)?], $value:expr, $expected:expr) => {
+        #[test]
+        fn $name() {
+            let sim = Simulator::$create($($vref)?);
+            let mut dac = Mcp49xx::$create(sim.spi());
+            assert_eq!(sim.output_voltage(Channel::Ch0), None);
+            dac.send(Command::default().value($value)).unwrap();
+            assert_close(sim.output_voltage(Channel::Ch0), $expected);
+            assert_eq!(sim.output_voltage(Channel::Ch1), None);
+        }
+    };
+}
+
+output_test!(mcp4801, new_mcp4801, [], 128, 1.024);
+output_test!(mcp4802, new_mcp4802, [], 64, 0.512);
+output_test!(mcp4811, new_mcp4811, [], 512, 1.024);
+output_test!(mcp4812, new_mcp4812, [], 1023, 2.046);
+output_test!(mcp4821, new_mcp4821, [], 2048, 1.024);
+output_test!(mcp4822, new_mcp4822, [], 1, 0.0005);
+output_test!(mcp4901, new_mcp4901, [3.3], 128, 1.65);
+output_test!(mcp4902, new_mcp4902, [3.3], 255, 3.287_109_4);
+output_test!(mcp4911, new_mcp4911, [5.0], 256, 1.25);
+output_test!(mcp4912, new_mcp4912, [5.0], 768, 3.75);
+output_test!(mcp4921, new_mcp4921, [2.5], 4095, 2.499_39);
+output_test!(mcp4922, new_mcp4922, [2.5], 1024, 0.625);
+
+#[test]
+fn models_registers_of_each_channel() {
+    let sim = Simulator::new_mcp4912(2.0);
+    let mut dac = Mcp49xx::new_mcp4912(sim.spi());
+    let cmd = Command::default()
+        .channel(Channel::Ch1)
+        .buffered()
+        .double_gain()
+        .value(256);
+    dac.send(cmd).unwrap();
+    assert_eq!(sim.input_register(Channel::Ch1), cmd);
+    assert_eq!(sim.dac_register(Channel::Ch1), cmd);
+    assert_close(sim.output_voltage(Channel::Ch1), 1.0);
+    assert_eq!(sim.output_voltage(Channel::Ch0), None);
+}
+
+#[test]
+fn can_set_vref_per_channel() {
+    let sim = Simulator::new_mcp4922(2.0);
+    sim.set_vref(Channel::Ch1, 4.0);
+    let mut dac = Mcp49xx::new_mcp4922(sim.spi());
+    dac.send(Command::default().value(2048)).unwrap();
+    dac.send(Command::default().channel(Channel::Ch1).value(2048))
+        .unwrap();
+    assert_close(sim.output_voltage(Channel::Ch0), 1.0);
+    assert_close(sim.output_voltage(Channel::Ch1), 2.0);
+}
+
+#[test]
+fn channel_can_be_shut_down() {
+    let sim = Simulator::new_mcp4821();
+    let mut dac = Mcp49xx::new_mcp4821(sim.spi());
+    dac.send(Command::default().value(100)).unwrap();
+    dac.send(Command::default().value(100).shutdown()).unwrap();
+    assert_eq!(sim.output_voltage(Channel::Ch0), None);
+    assert_eq!(sim.dac_register(Channel::Ch0).get_value(), 100);
+}
+
+#[test]
+fn ldac_latches_outputs() {
+    let sim = Simulator::new_mcp4822();
+    let mut ldac = sim.ldac();
+    ldac.set_high().unwrap();
+    let mut dac = Mcp49xx::new_mcp4822(sim.spi());
+    dac.send(Command::default().value(1000)).unwrap();
+    assert_eq!(sim.input_register(Channel::Ch0).get_value(), 1000);
+    assert_eq!(sim.output_voltage(Channel::Ch0), None);
+    ldac.set_low().unwrap();
+    assert_close(sim.output_voltage(Channel::Ch0), 0.5);
+}
+
+#[test]
+fn can_send_synchronized() {
+    let sim = Simulator::new_mcp4802();
+    let mut dac = Mcp49xx::new_mcp4802(sim.spi()).with_ldac(sim.ldac());
+    dac.send_synchronized(
+        Command::default().value(10),
+        Command::default().value(20),
+        &mut NoopDelay,
+    )
+    .unwrap();
+    assert_close(sim.output_voltage(Channel::Ch0), 0.08);
+    assert_close(sim.output_voltage(Channel::Ch1), 0.16);
+}
+
+#[test]
+fn shdn_pin_shuts_down_outputs() {
+    let sim = Simulator::new_mcp4922(4.096);
+    let mut dac = Mcp49xx::new_mcp4922(sim.spi()).with_shdn(sim.shdn().unwrap());
+    dac.send(Command::default().value(3000)).unwrap();
+    dac.send(Command::default().channel(Channel::Ch1).value(1000))
+        .unwrap();
+    dac.hardware_shutdown().unwrap();
+    assert_eq!(sim.output_voltage(Channel::Ch0), None);
+    assert_eq!(sim.output_voltage(Channel::Ch1), None);
+    dac.hardware_wake().unwrap();
+    assert_close(sim.output_voltage(Channel::Ch0), 3.0);
+    assert_close(sim.output_voltage(Channel::Ch1), 1.0);
+}
+
+#[test]
+fn shdn_pin_is_only_available_in_devices_with_it() {
+    assert!(Simulator::new_mcp4801().shdn().is_some());
+    assert!(Simulator::new_mcp4821().shdn().is_some());
+    assert!(Simulator::new_mcp4802().shdn().is_none());
+    assert!(Simulator::new_mcp4822().shdn().is_none());
+    assert!(Simulator::new_mcp4901(1.0).shdn().is_none());
+    assert!(Simulator::new_mcp4921(1.0).shdn().is_none());
+    assert!(Simulator::new_mcp4902(1.0).shdn().is_some());
+    assert!(Simulator::new_mcp4912(1.0).shdn().is_some());
+}
+
+#[test]
+fn single_channel_device_ignores_channel_1() {
+    let sim = Simulator::new_mcp4901(1.0);
+    let mut spi = sim.spi();
+    spi.write(&[0b1011_1111, 0b1111_0000]).unwrap();
+    assert_eq!(
+        sim.input_register(Channel::Ch0),
+        Command::default().shutdown()
+    );
+    assert_eq!(sim.output_voltage(Channel::Ch1), None);
+}
+
+#[test]
+fn ignores_frames_with_wrong_length() {
+    let sim = Simulator::new_mcp4811();
+    let mut spi = sim.spi();
+    spi.write(&[0b0011_1111]).unwrap();
+    spi.write(&[0b0011_1111, 0b1111_1100, 0]).unwrap();
+    assert_eq!(sim.output_voltage(Channel::Ch0), None);
+}
+
+#[test]
+fn can_use_spi_bus_and_cs_pin() {
+    use embedded_hal::spi::SpiBus;
+    let sim = Simulator::new_mcp4811();
+    let mut spi = sim.spi_bus();
+    let mut cs = sim.cs();
+    cs.set_low().unwrap();
+    spi.write(&[0b0011_0111]).unwrap();
+    spi.write(&[0b1111_1100]).unwrap();
+    cs.set_high().unwrap();
+    assert_close(sim.output_voltage(Channel::Ch0), 1.022);
+}
+
+#[cfg(feature = "eh02")]
+#[test]
+fn can_use_eh02_driver() {
+    let sim = Simulator::new_mcp4812();
+    let mut spi = sim.spi_bus();
+    let mut dac = mcp49xx::eh02::Mcp49xx::new_mcp4812(sim.cs());
+    dac.send(&mut spi, Command::default().channel(Channel::Ch1).value(1))
+        .unwrap();
+    assert_close(sim.output_voltage(Channel::Ch1), 0.002);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn can_use_async_driver() {
+    let sim = Simulator::new_mcp4911(1.024);
+    let mut dac = mcp49xx::asynch::Mcp49xx::new_mcp4911(sim.spi());
+    dac.send(Command::default().value(1000)).await.unwrap();
+    assert_close(sim.output_voltage(Channel::Ch0), 1.0);
+}
+
+#[test]
+fn analog_output_slews_and_settles() {
+    let sim = Simulator::new_mcp4921(4.096);
+    let mut params = sim.analog_parameters();
+    params.inl = 0.0;
+    params.dnl = 0.0;
+    sim.set_analog_parameters(params);
+    let mut dac = Mcp49xx::new_mcp4921(sim.spi());
+    dac.send(Command::default().value(2000)).unwrap();
+    assert_eq!(sim.analog_voltage(Channel::Ch0), 0.0);
+    let mut samples = [0.0; 3];
+    sim.record(Channel::Ch0, 1000, &mut samples);
+    assert_eq!(sim.now_ns(), 3000);
+    assert_close(Some(samples[1]), 0.55);
+    assert_close(Some(samples[2]), 1.1);
+    sim.advance(10_000);
+    assert!((sim.analog_voltage(Channel::Ch0) - 2.0).abs() < 0.0005);
+}
+
+#[test]
+fn analog_output_includes_nonlinearity() {
+    let sim = Simulator::new_mcp4922(4.096);
+    let mut dac = Mcp49xx::new_mcp4922(sim.spi());
+    dac.send(Command::default().value(2048)).unwrap();
+    sim.advance(100_000);
+    // 2 LSB INL and 0.2 LSB DNL at mid-scale
+    assert_close(Some(sim.analog_voltage(Channel::Ch0)), 2.0499);
+    assert_close(sim.output_voltage(Channel::Ch0), 2.048);
+}
+
+#[test]
+fn shutdown_output_is_set_by_load() {
+    let sim = Simulator::new_mcp4802();
+    sim.set_load(Channel::Ch1, 3.0, 100_000.0);
+    assert_close(Some(sim.analog_voltage(Channel::Ch1)), 2.5);
+    assert_eq!(sim.analog_voltage(Channel::Ch0), 0.0);
+}
+
+#[test]
+fn delay_advances_virtual_clock() {
+    let sim = Simulator::new_mcp4802();
+    let mut dac = Mcp49xx::new_mcp4802(sim.spi()).with_ldac(sim.ldac());
+    dac.send_synchronized(
+        Command::default().value(128),
+        Command::default().value(128),
+        &mut sim.delay(),
+    )
+    .unwrap();
+    assert_eq!(sim.now_ns(), 140);
+    // the outputs have been slewing for 100ns
+    assert_close(Some(sim.analog_voltage(Channel::Ch0)), 0.055);
+    assert_close(Some(sim.analog_voltage(Channel::Ch1)), 0.055);
+}
+
+#[test]
+fn typed_code_is_not_scaled_by_value_policy() {
+    let sim = Simulator::new_mcp4921(4.096);
+    let mut dac = Mcp49xx::new_mcp4921(sim.spi());
+    dac.set_value_policy(ValuePolicy::Scale16Bit);
+    dac.send_command(dac.command().code(Code::MAX)).unwrap();
+    assert_eq!(sim.dac_register(Channel::Ch0).get_value(), 4095);
+    dac.send(Command::default().value(0xFFFF)).unwrap();
+    assert_eq!(sim.dac_register(Channel::Ch0).get_value(), 4095);
+}
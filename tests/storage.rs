@@ -0,0 +1,119 @@
+#![cfg(feature = "storage")]
+
+use embedded_storage::nor_flash::{
+    ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+use mcp49xx::{Calibration, CalibrationData, CalibrationDataError, StorageError};
+
+const CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FlashError(NorFlashErrorKind);
+
+impl NorFlashError for FlashError {
+    fn kind(&self) -> NorFlashErrorKind {
+        self.0
+    }
+}
+
+/// NOR flash in RAM with the usual alignment restrictions
+struct RamFlash {
+    data: [u8; CAPACITY],
+}
+
+impl RamFlash {
+    fn new() -> Self {
+        RamFlash {
+            data: [0; CAPACITY],
+        }
+    }
+}
+
+impl ErrorType for RamFlash {
+    type Error = FlashError;
+}
+
+impl ReadNorFlash for RamFlash {
+    const READ_SIZE: usize = 4;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), FlashError> {
+        embedded_storage::nor_flash::check_read(self, offset, bytes.len()).map_err(FlashError)?;
+        let offset = offset as usize;
+        bytes.copy_from_slice(&self.data[offset..offset + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        CAPACITY
+    }
+}
+
+impl NorFlash for RamFlash {
+    const WRITE_SIZE: usize = 8;
+    const ERASE_SIZE: usize = 256;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), FlashError> {
+        embedded_storage::nor_flash::check_erase(self, from, to).map_err(FlashError)?;
+        self.data[from as usize..to as usize].fill(0xFF);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), FlashError> {
+        embedded_storage::nor_flash::check_write(self, offset, bytes.len()).map_err(FlashError)?;
+        let offset = offset as usize;
+        for (cell, byte) in self.data[offset..offset + bytes.len()]
+            .iter_mut()
+            .zip(bytes)
+        {
+            // NOR flash can only clear bits
+            *cell &= *byte;
+        }
+        Ok(())
+    }
+}
+
+fn example() -> CalibrationData {
+    let cal = Calibration::new(0.5, 0.998)
+        .with_points(&[(100, 101), (4000, 3998)])
+        .unwrap();
+    CalibrationData {
+        calibration: [Some(cal), Some(Calibration::new(-2.0, 1.0))],
+        vref: [Some(2.5), None],
+    }
+}
+
+#[test]
+fn can_save_and_load() {
+    let mut flash = RamFlash::new();
+    example().save(&mut flash, 256).unwrap();
+    assert_eq!(CalibrationData::load(&mut flash, 256), Ok(example()));
+}
+
+#[test]
+fn can_overwrite() {
+    let mut flash = RamFlash::new();
+    CalibrationData::default().save(&mut flash, 0).unwrap();
+    example().save(&mut flash, 0).unwrap();
+    assert_eq!(CalibrationData::load(&mut flash, 0), Ok(example()));
+}
+
+#[test]
+fn cannot_load_erased_flash() {
+    let mut flash = RamFlash::new();
+    flash.erase(0, 256).unwrap();
+    assert_eq!(
+        CalibrationData::load(&mut flash, 0),
+        Err(StorageError::Data(CalibrationDataError::InvalidHeader))
+    );
+}
+
+#[test]
+fn cannot_save_unaligned() {
+    let mut flash = RamFlash::new();
+    assert_eq!(
+        example().save(&mut flash, 8),
+        Err(StorageError::Flash(FlashError(
+            NorFlashErrorKind::NotAligned
+        )))
+    );
+}